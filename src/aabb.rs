@@ -17,7 +17,8 @@ impl AABB {
         }
     }
 
-    pub fn hit(&self, r: &Ray, tmin: f32, tmax: f32) -> bool {
+    // Clips [tmin, tmax] to the slab of each axis in turn, the ray hits if anything is left
+    pub fn hit(&self, r: &Ray, mut tmin: f32, mut tmax: f32) -> bool {
         for i in 0..3 {
             let inv_d = 1.0 / r.direction()[i];
             let mut t0 = (self.min[i] - r.origin()[i]) * inv_d;
@@ -26,13 +27,13 @@ impl AABB {
                 std::mem::swap(&mut t0, &mut t1);
             }
 
-            let tmin = if t0 > tmin {
+            tmin = if t0 > tmin {
                 t0
             } else {
                 tmin
             };
 
-            let tmax = if t1 < tmax {
+            tmax = if t1 < tmax {
                 t1
             } else {
                 tmax
@@ -60,6 +61,15 @@ impl AABB {
         AABB::new(small, big)
     }
 
+    pub fn centroid(&self) -> Vector3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        let d = self.max - self.min;
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
     pub fn min(&self) -> Vector3 {
         self.min
     }
@@ -0,0 +1,124 @@
+extern crate tdmath;
+
+use tdmath::Vector3;
+
+// Per-pixel sample budget for adaptive sampling. Sampling a pixel stops once the standard
+// error of its luminance relative to the mean drops below threshold, so bright and dark
+// pixels converge to the same visible noise level.
+#[derive(Debug, Copy, Clone)]
+pub struct AdaptiveSampling {
+    min_samples: u32,
+    max_samples: u32,
+    threshold: f32,
+}
+
+impl AdaptiveSampling {
+    // max_samples is never exceeded, min_samples is lowered to it if needed
+    pub fn new(min_samples: u32, max_samples: u32, threshold: f32) -> Self {
+        let min_samples = if min_samples < 2 {
+            2
+        } else {
+            min_samples
+        };
+
+        let min_samples = if min_samples > max_samples {
+            max_samples
+        } else {
+            min_samples
+        };
+
+        AdaptiveSampling {
+            min_samples,
+            max_samples,
+            threshold,
+        }
+    }
+
+    pub fn min_samples(&self) -> u32 {
+        self.min_samples
+    }
+
+    pub fn max_samples(&self) -> u32 {
+        self.max_samples
+    }
+
+    pub fn threshold(&self) -> f32 {
+        self.threshold
+    }
+
+    pub fn converged(&self, stats: &PixelStats) -> bool {
+        stats.count() >= self.min_samples && stats.relative_error() < self.threshold
+    }
+}
+
+// Running mean and variance of a pixel's samples using Welford's algorithm
+pub struct PixelStats {
+    count: u32,
+    sum: Vector3,
+    mean: f32,
+    m2: f32,
+}
+
+impl PixelStats {
+    pub fn new() -> Self {
+        PixelStats {
+            count: 0,
+            sum: Vector3::zero(),
+            mean: 0.0,
+            m2: 0.0,
+        }
+    }
+
+    pub fn add(&mut self, sample: Vector3) {
+        self.count += 1;
+        self.sum = self.sum + sample;
+
+        let value = luminance(sample);
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f32;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn mean(&self) -> Vector3 {
+        if self.count > 0 {
+            self.sum / self.count as f32
+        } else {
+            Vector3::zero()
+        }
+    }
+
+    pub fn variance(&self) -> f32 {
+        if self.count > 1 {
+            self.m2 / (self.count - 1) as f32
+        } else {
+            0.0
+        }
+    }
+
+    pub fn standard_error(&self) -> f32 {
+        if self.count > 0 {
+            (self.variance() / self.count as f32).sqrt()
+        } else {
+            ::std::f32::MAX
+        }
+    }
+
+    // Standard error over the mean, zero for pixels that have only seen black
+    pub fn relative_error(&self) -> f32 {
+        if self.count == 0 {
+            ::std::f32::MAX
+        } else if self.mean > 0.0 {
+            self.standard_error() / self.mean
+        } else {
+            0.0
+        }
+    }
+}
+
+fn luminance(color: Vector3) -> f32 {
+    (0.2126 * color.r() + 0.7152 * color.g() + 0.0722 * color.b()).max(0.0)
+}
@@ -0,0 +1,95 @@
+extern crate tdmath;
+
+use tdmath::{Vector3, Ray};
+use world::World;
+use hitable::HitRecord;
+
+// Auxiliary values gathered at the first surface hit by camera rays. Ids are -1 where
+// nothing was hit.
+#[derive(Debug, Copy, Clone)]
+pub struct AovPixel {
+    pub depth: f32,
+    pub normal: Vector3,
+    pub albedo: Vector3,
+    pub object_id: f32,
+    pub material_id: f32,
+}
+
+impl AovPixel {
+    pub fn new() -> Self {
+        AovPixel {
+            depth: 0.0,
+            normal: Vector3::zero(),
+            albedo: Vector3::zero(),
+            object_id: -1.0,
+            material_id: -1.0,
+        }
+    }
+
+    pub fn from_ray(ray: Ray, world: &World) -> Self {
+        match world.hit_object(ray, 0.001, ::std::f32::MAX) {
+            Some((hit, object_id)) => AovPixel::from_hit(ray, &hit, object_id, world),
+            None => AovPixel::new(),
+        }
+    }
+
+    // AOVs of hit, the first surface found by ray
+    pub fn from_hit(ray: Ray, hit: &HitRecord, object_id: usize, world: &World) -> Self {
+        AovPixel {
+            depth: hit.t() * ray.direction().length(),
+            normal: hit.normal().normalized(),
+            albedo: hit.material.albedo(hit),
+            object_id: object_id as f32,
+            material_id: world.material_id(object_id) as f32,
+        }
+    }
+}
+
+// Averages the continuous AOVs of a pixel's samples. Ids are taken from the first sample.
+pub struct AovAccumulator {
+    count: u32,
+    pixel: AovPixel,
+}
+
+impl AovAccumulator {
+    pub fn new() -> Self {
+        AovAccumulator {
+            count: 0,
+            pixel: AovPixel::new(),
+        }
+    }
+
+    pub fn add(&mut self, sample: AovPixel) {
+        if self.count == 0 {
+            self.pixel = sample;
+        } else {
+            self.pixel.depth += sample.depth;
+            self.pixel.normal = self.pixel.normal + sample.normal;
+            self.pixel.albedo = self.pixel.albedo + sample.albedo;
+        }
+
+        self.count += 1;
+    }
+
+    pub fn result(&self) -> AovPixel {
+        if self.count == 0 {
+            return AovPixel::new();
+        }
+
+        let n = self.count as f32;
+        let normal = self.pixel.normal / n;
+        let normal = if normal.length_squared() > 0.0 {
+            normal.normalized()
+        } else {
+            normal
+        };
+
+        AovPixel {
+            depth: self.pixel.depth / n,
+            normal,
+            albedo: self.pixel.albedo / n,
+            object_id: self.pixel.object_id,
+            material_id: self.pixel.material_id,
+        }
+    }
+}
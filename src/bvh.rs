@@ -1,134 +1,308 @@
-extern crate rand;
 extern crate tdmath;
 
 use hitable::*;
 use aabb::AABB;
-use tdmath::Ray;
-use rand::Rng;
+use tdmath::{Vector3, Ray};
+use sampler::Sampler;
+use std::f32;
+use std::cmp::Ordering;
 
+const SAH_BINS: usize = 12;
+const MAX_LEAF_SIZE: usize = 4;
+// Past this depth nodes are split at the median so the traversal stack can't overflow
+const MAX_SAH_DEPTH: usize = 32;
+const STACK_SIZE: usize = 64;
+
+// Bounding volume hierarchy stored as a flat array of nodes in depth-first order.
+// The first child of an interior node immediately follows it, the second child is at `offset`.
+// Leaves reference the range `offset..offset + count` of `primitives`.
 pub struct BVH {
-    left: Option<Box<Hitable>>,
-    right: Option<Box<Hitable>>,
+    primitives: Vec<Box<Hitable>>,
+    indices: Vec<usize>,
+    nodes: Vec<BVHNode>,
+}
+
+struct BVHNode {
     bbox: AABB,
+    offset: usize,
+    count: usize,
+    axis: usize,
 }
 
-impl BVH {
-    pub fn from_list(mut hitables: Vec<Box<Hitable>>, t0: f32, t1: f32) -> Self {
-        hitables = BVH::sort_list_by_random_axis(hitables, t0, t1);
+struct BuildPrimitive {
+    index: usize,
+    bbox: AABB,
+    centroid: Vector3,
+}
 
-        if hitables.len() == 1 {
-            let left = hitables.remove(0);
-            let bbox = left.bounding_box(t0, t1).unwrap();
+impl BVH {
+    pub fn from_list(hitables: Vec<Box<Hitable>>, t0: f32, t1: f32) -> Self {
+        let mut build: Vec<BuildPrimitive> = hitables.iter().enumerate().map(|(index, hitable)| {
+            let bbox = match hitable.bounding_box(t0, t1) {
+                Some(bbox) => bbox,
+                None => panic!("Bounding box not created for hitable"),
+            };
 
-            return BVH {
-                left: Some(left),
-                right: None,
-                bbox
-            }
-        } else if hitables.len() == 2 {
-            let left = hitables.remove(0);
-            let right = hitables.remove(0);
-            let bbox = AABB::surrounding_box(left.bounding_box(t0, t1).unwrap(), right.bounding_box(t0, t1).unwrap());
-
-            return BVH {
-                left: Some(left),
-                right: Some(right),
+            BuildPrimitive {
+                index,
                 bbox,
-            };
-        } else {
-            let n = hitables.len();
-            let mut left_list = hitables;
-            let right_list = left_list.split_off(n / 2);
+                centroid: bbox.centroid(),
+            }
+        }).collect();
 
-            let left = BVH::from_list(left_list, t0, t1);
-            let right = BVH::from_list(right_list, t0, t1);
-            let bbox = AABB::surrounding_box(left.bounding_box(t0, t1).unwrap(), right.bounding_box(t0, t1).unwrap());
+        let mut nodes = Vec::with_capacity(hitables.len() * 2);
+        let mut order = Vec::with_capacity(hitables.len());
+        if build.len() > 0 {
+            BVH::build_recursive(&mut build, &mut nodes, &mut order, 0);
+        }
 
-            return BVH {
-                left: Some(Box::new(left)),
-                right: Some(Box::new(right)),
-                bbox,
-            };
+        let mut slots: Vec<Option<Box<Hitable>>> = hitables.into_iter().map(Some).collect();
+        let primitives = order.iter().map(|&i| slots[i].take().unwrap()).collect();
+
+        BVH {
+            primitives,
+            indices: order,
+            nodes,
         }
     }
 
-    fn sort_list_by_random_axis(mut list: Vec<Box<Hitable>>, t0: f32, t1: f32) -> Vec<Box<Hitable>> {
-        let mut rng = rand::thread_rng();
-        match (rng.gen::<f32>() * 3.0) as i32 {
-            0 => {
-                list.sort_by(|a, b| {
-                    if let (Some(box_a), Some(box_b)) = (a.bounding_box(t0, t1), b.bounding_box(t0, t1)) {
-                        (box_a.min().x as i32).cmp(&(box_b.min().x as i32))
-                    } else {
-                        panic!("Bounding box not created for hitable")
-                    }
-                })
-            },
-            1 => {
-                list.sort_by(|a, b| {
-                    if let (Some(box_a), Some(box_b)) = (a.bounding_box(t0, t1), b.bounding_box(t0, t1)) {
-                        (box_a.min().y as i32).cmp(&(box_b.min().y as i32))
-                    } else {
-                        panic!("Bounding box not created for hitable")
+    // Number of hitables stored in the leaves of this tree
+    pub fn len(&self) -> usize {
+        self.primitives.len()
+    }
+
+    // Like hit, but also returns the position of the hit primitive in the list passed to from_list
+    pub fn hit_with_index(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<(HitRecord, usize)> {
+        if self.nodes.len() == 0 {
+            return None;
+        }
+
+        let direction = ray.direction();
+        let dir_is_neg = [direction.x < 0.0, direction.y < 0.0, direction.z < 0.0];
+
+        let mut hit_record: Option<(HitRecord, usize)> = None;
+        let mut closest_so_far = t_max;
+        let mut stack = [0usize; STACK_SIZE];
+        let mut stack_len = 0;
+        let mut node_index = 0;
+
+        loop {
+            let node = &self.nodes[node_index];
+            if node.bbox.hit(&ray, t_min, closest_so_far) {
+                if node.count > 0 {
+                    for i in node.offset..node.offset + node.count {
+                        if let Some(hit) = self.primitives[i].hit(ray, t_min, closest_so_far) {
+                            closest_so_far = hit.t();
+                            hit_record = Some((hit, self.indices[i]));
+                        }
                     }
-                })
-            },
-            _ => {
-                list.sort_by(|a, b| {
-                    if let (Some(box_a), Some(box_b)) = (a.bounding_box(t0, t1), b.bounding_box(t0, t1)) {
-                        (box_a.min().z as i32).cmp(&(box_b.min().z as i32))
+                } else {
+                    // Visit the child nearest the ray origin first so later boxes can be culled by closest_so_far
+                    if dir_is_neg[node.axis] {
+                        stack[stack_len] = node_index + 1;
+                        node_index = node.offset;
                     } else {
-                        panic!("Bounding box not created for hitable")
+                        stack[stack_len] = node.offset;
+                        node_index = node_index + 1;
                     }
-                })
+                    stack_len += 1;
+                    continue;
+                }
+            }
+
+            if stack_len == 0 {
+                break;
             }
+            stack_len -= 1;
+            node_index = stack[stack_len];
         }
 
-        list
+        hit_record
     }
-}
 
-impl Hitable for BVH {
-    fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
-        if self.bbox.hit(&ray, t_min, t_max) {
-            let hit_left = if let Some(l) = &self.left {
-                l.hit(ray, t_min, t_max)
-            } else {
-                None
-            };
+    fn build_recursive(prims: &mut [BuildPrimitive], nodes: &mut Vec<BVHNode>, order: &mut Vec<usize>, depth: usize) -> usize {
+        let mut bbox = prims[0].bbox;
+        for p in prims.iter() {
+            bbox = AABB::surrounding_box(bbox, p.bbox);
+        }
 
-            let hit_right = if let Some(r) = &self.right {
-                r.hit(ray, t_min, t_max)
-            } else {
-                None
-            };
+        let node_index = nodes.len();
+        nodes.push(BVHNode {
+            bbox,
+            offset: 0,
+            count: 0,
+            axis: 0,
+        });
 
-            match hit_left {
-                Some(l) => {
-                    match hit_right {
-                        Some(r) => {
-                            if l.t() < r.t() {
-                                return Some(l);
-                            } else {
-                                return Some(r);
-                            }
-                        },
-                        None => return Some(l),
-                    }
+        if prims.len() <= MAX_LEAF_SIZE {
+            nodes[node_index].offset = order.len();
+            nodes[node_index].count = prims.len();
+            for p in prims.iter() {
+                order.push(p.index);
+            }
+
+            return node_index;
+        }
+
+        let sah_split = if depth < MAX_SAH_DEPTH {
+            BVH::partition_by_sah(prims)
+        } else {
+            None
+        };
+
+        let (axis, mid) = match sah_split {
+            Some(split) => split,
+            None => BVH::partition_by_median(prims),
+        };
+
+        {
+            let (left, right) = prims.split_at_mut(mid);
+            BVH::build_recursive(left, nodes, order, depth + 1);
+            let second = BVH::build_recursive(right, nodes, order, depth + 1);
+            nodes[node_index].offset = second;
+            nodes[node_index].axis = axis;
+        }
+
+        node_index
+    }
+
+    // Finds the best split using the surface area heuristic evaluated over a fixed number of
+    // centroid bins on each axis. Returns the split axis and the number of primitives on the left.
+    fn partition_by_sah(prims: &mut [BuildPrimitive]) -> Option<(usize, usize)> {
+        let (centroid_min, centroid_max) = centroid_bounds(prims);
+
+        let mut best_split: Option<(usize, usize)> = None;
+        let mut best_cost = f32::MAX;
+
+        for axis in 0..3 {
+            let extent = centroid_max[axis] - centroid_min[axis];
+            if extent <= 0.0 {
+                continue;
+            }
+
+            let mut counts = [0usize; SAH_BINS];
+            let mut bounds: [Option<AABB>; SAH_BINS] = [None; SAH_BINS];
+            for p in prims.iter() {
+                let bin = sah_bin(p.centroid[axis], centroid_min[axis], extent);
+                counts[bin] += 1;
+                bounds[bin] = surrounding_option(bounds[bin], Some(p.bbox));
+            }
+
+            // Sweep from the right so each split can be costed in a single pass from the left
+            let mut right_area = [0.0f32; SAH_BINS];
+            let mut right_count = [0usize; SAH_BINS];
+            let mut accum: Option<AABB> = None;
+            let mut count = 0;
+            for bin in (1..SAH_BINS).rev() {
+                accum = surrounding_option(accum, bounds[bin]);
+                count += counts[bin];
+                right_area[bin] = accum.map_or(0.0, |b| b.surface_area());
+                right_count[bin] = count;
+            }
+
+            let mut accum: Option<AABB> = None;
+            let mut count = 0;
+            for split in 1..SAH_BINS {
+                accum = surrounding_option(accum, bounds[split - 1]);
+                count += counts[split - 1];
+                if count == 0 || right_count[split] == 0 {
+                    continue;
                 }
-                None => {
-                    match hit_right {
-                        Some(r) => return Some(r),
-                        None => return None,
-                    }
+
+                let left_area = accum.map_or(0.0, |b| b.surface_area());
+                let cost = count as f32 * left_area + right_count[split] as f32 * right_area[split];
+                if cost < best_cost {
+                    best_cost = cost;
+                    best_split = Some((axis, split));
                 }
             }
+        }
+
+        match best_split {
+            Some((axis, split)) => {
+                let min = centroid_min[axis];
+                let extent = centroid_max[axis] - min;
+                prims.sort_by_key(|p| sah_bin(p.centroid[axis], min, extent));
+                let mid = prims.iter().filter(|p| sah_bin(p.centroid[axis], min, extent) < split).count();
+
+                Some((axis, mid))
+            },
+            None => None
+        }
+    }
+
+    fn partition_by_median(prims: &mut [BuildPrimitive]) -> (usize, usize) {
+        let (centroid_min, centroid_max) = centroid_bounds(prims);
+        let extent = centroid_max - centroid_min;
+        let axis = if extent.x > extent.y && extent.x > extent.z {
+            0
+        } else if extent.y > extent.z {
+            1
         } else {
-            return None;
+            2
+        };
+
+        prims.sort_by(|a, b| a.centroid[axis].partial_cmp(&b.centroid[axis]).unwrap_or(Ordering::Equal));
+
+        (axis, prims.len() / 2)
+    }
+}
+
+fn centroid_bounds(prims: &[BuildPrimitive]) -> (Vector3, Vector3) {
+    let mut min = prims[0].centroid;
+    let mut max = prims[0].centroid;
+    for p in prims.iter() {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(p.centroid[axis]);
+            max[axis] = max[axis].max(p.centroid[axis]);
+        }
+    }
+
+    (min, max)
+}
+
+fn sah_bin(value: f32, min: f32, extent: f32) -> usize {
+    let bin = ((value - min) / extent * SAH_BINS as f32) as usize;
+    if bin >= SAH_BINS {
+        SAH_BINS - 1
+    } else {
+        bin
+    }
+}
+
+fn surrounding_option(a: Option<AABB>, b: Option<AABB>) -> Option<AABB> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(AABB::surrounding_box(a, b)),
+        (Some(a), None) => Some(a),
+        (None, b) => b,
+    }
+}
+
+impl Hitable for BVH {
+    fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        match self.hit_with_index(ray, t_min, t_max) {
+            Some((hit, _)) => Some(hit),
+            None => None,
         }
     }
 
     fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
-        Some(self.bbox)
+        self.nodes.first().map(|node| node.bbox)
     }
-}
\ No newline at end of file
+
+    fn pdf_value(&self, origin: Vector3, v: Vector3) -> f32 {
+        let weight = 1.0 / self.primitives.len() as f32;
+        let mut sum = 0.0;
+        for primitive in &self.primitives {
+            sum += weight * primitive.pdf_value(origin, v);
+        }
+
+        sum
+    }
+
+    fn random(&self, origin: Vector3, sampler: &mut Sampler) -> Vector3 {
+        let index = ((sampler.next_1d() * self.primitives.len() as f32) as usize).min(self.primitives.len() - 1);
+
+        self.primitives[index].random(origin, sampler)
+    }
+}
@@ -1,11 +1,10 @@
-extern crate rand;
 extern crate tdmath;
 extern crate toml;
 
 use tdmath::Vector3;
 use tdmath::Ray;
 use std::f32::consts;
-use rand::Rng;
+use sampler::{Sampler, sample_unit_disk};
 use toml::Value;
 
 #[derive(Debug, Copy, Clone)]
@@ -47,12 +46,11 @@ impl Camera {
         }
     }
 
-    pub fn get_ray(&self, u: f32, v: f32) -> Ray {
-        let rd = Vector3::random_in_unit_disk() * self.lens_radius;
+    pub fn get_ray(&self, u: f32, v: f32, sampler: &mut Sampler) -> Ray {
+        let rd = sample_unit_disk(sampler.next_2d()) * self.lens_radius;
         let offset = self.u * rd.x + self.v * rd.y;
 
-        let mut rng = rand::thread_rng();
-        let time = self.time0 + (self.time1 - self.time0) * rng.gen::<f32>();
+        let time = self.time0 + (self.time1 - self.time0) * sampler.next_1d();
 
         Ray::new(self.origin + offset,
             self.lower_left_corner + self.horizontal * u + self.vertical * v - self.origin - offset, time)
@@ -0,0 +1,190 @@
+extern crate tdmath;
+extern crate toml;
+
+use tdmath::Vector3;
+use toml::Value;
+use framebuffer::FrameBuffer;
+use aov::AovPixel;
+
+// Edge-avoiding A-Trous wavelet filter (Dammertz et al. 2010). Each pass blurs with a 5x5
+// B3-spline kernel whose taps spread twice as far apart as in the previous pass, weighted
+// down where colour, normal or depth differ so edges survive. Colour is divided by albedo
+// before filtering so texture detail isn't blurred away.
+// Past this many iterations the taps are spread further apart than any image is wide
+const MAX_ITERATIONS: u32 = 16;
+// Smaller sigmas would divide by zero when neighbouring pixels match exactly
+const MIN_SIGMA: f32 = 1e-4;
+
+pub struct Denoiser {
+    iterations: u32,
+    sigma_color: f32,
+    sigma_normal: f32,
+    sigma_depth: f32,
+}
+
+impl Denoiser {
+    pub fn new(iterations: u32, sigma_color: f32, sigma_normal: f32, sigma_depth: f32) -> Self {
+        let mut denoiser = Denoiser {
+            iterations: 0,
+            sigma_color: 0.0,
+            sigma_normal: 0.0,
+            sigma_depth: 0.0,
+        };
+        denoiser.set_iterations(iterations);
+        denoiser.set_sigma_color(sigma_color);
+        denoiser.set_sigma_normal(sigma_normal);
+        denoiser.set_sigma_depth(sigma_depth);
+
+        denoiser
+    }
+
+    // Reads the denoise_* keys of a [film] table, missing ones keep their default
+    pub fn from_toml(film_data: &Value) -> Self {
+        let mut denoiser = Denoiser::default();
+
+        if let Some(iterations) = film_data.get("denoise_iterations") {
+            denoiser.set_iterations(iterations.as_integer().unwrap() as u32);
+        }
+
+        if let Some(sigma) = film_data.get("denoise_sigma_color") {
+            denoiser.set_sigma_color(sigma.as_float().unwrap() as f32);
+        }
+
+        if let Some(sigma) = film_data.get("denoise_sigma_normal") {
+            denoiser.set_sigma_normal(sigma.as_float().unwrap() as f32);
+        }
+
+        if let Some(sigma) = film_data.get("denoise_sigma_depth") {
+            denoiser.set_sigma_depth(sigma.as_float().unwrap() as f32);
+        }
+
+        denoiser
+    }
+
+    // Each iteration doubles the radius of the filter, up to MAX_ITERATIONS iterations
+    pub fn set_iterations(&mut self, iterations: u32) {
+        self.iterations = iterations.min(MAX_ITERATIONS);
+    }
+
+    // Larger sigmas blur across bigger differences in colour, normal and relative depth.
+    // Sigmas below MIN_SIGMA are raised to it.
+    pub fn set_sigma_color(&mut self, sigma_color: f32) {
+        self.sigma_color = sigma_color.max(MIN_SIGMA);
+    }
+
+    pub fn set_sigma_normal(&mut self, sigma_normal: f32) {
+        self.sigma_normal = sigma_normal.max(MIN_SIGMA);
+    }
+
+    pub fn set_sigma_depth(&mut self, sigma_depth: f32) {
+        self.sigma_depth = sigma_depth.max(MIN_SIGMA);
+    }
+
+    // Returns a copy of frame unchanged if it was rendered without AOVs
+    pub fn denoise(&self, frame: &FrameBuffer) -> FrameBuffer {
+        let aovs = match frame.aovs() {
+            Some(aovs) => aovs,
+            None => return frame.clone(),
+        };
+
+        let width = frame.width() as i32;
+        let height = frame.height() as i32;
+
+        let mut irradiance: Vec<Vector3> = frame.pixels().iter().zip(aovs.iter()).map(|(&c, a)| {
+            demodulate(c, a.albedo)
+        }).collect();
+
+        let kernel = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+        let mut sigma_color = self.sigma_color;
+
+        for iteration in 0..self.iterations {
+            let step = 1 << iteration;
+            let mut filtered = Vec::with_capacity(irradiance.len());
+
+            for y in 0..height {
+                for x in 0..width {
+                    let p = (y * width + x) as usize;
+                    let center = irradiance[p];
+                    let center_aov = aovs[p];
+
+                    let mut sum = Vector3::zero();
+                    let mut weight_sum = 0.0;
+
+                    for j in 0..5 {
+                        for i in 0..5 {
+                            let qx = x + (i - 2) * step;
+                            let qy = y + (j - 2) * step;
+                            if qx < 0 || qx >= width || qy < 0 || qy >= height {
+                                continue;
+                            }
+
+                            let q = (qy * width + qx) as usize;
+                            let weight = kernel[i as usize] * kernel[j as usize] *
+                                self.edge_weight(center, irradiance[q], &center_aov, &aovs[q], sigma_color);
+
+                            sum = sum + irradiance[q] * weight;
+                            weight_sum += weight;
+                        }
+                    }
+
+                    if weight_sum > 0.0 {
+                        filtered.push(sum / weight_sum);
+                    } else {
+                        filtered.push(center);
+                    }
+                }
+            }
+
+            irradiance = filtered;
+            sigma_color *= 0.5;
+        }
+
+        let mut result = frame.clone();
+        for y in 0..frame.height() {
+            for x in 0..frame.width() {
+                let p = (y * frame.width() + x) as usize;
+                result.put_pixel(x, y, remodulate(irradiance[p], aovs[p].albedo));
+            }
+        }
+
+        result
+    }
+
+    fn edge_weight(&self, c0: Vector3, c1: Vector3, a0: &AovPixel, a1: &AovPixel, sigma_color: f32) -> f32 {
+        // Never mix geometry from different objects or with the background
+        if a0.object_id != a1.object_id {
+            return 0.0;
+        }
+
+        let color_distance = (c0 - c1).length_squared();
+        let color_weight = (-color_distance / (sigma_color * sigma_color)).exp();
+
+        let normal_distance = (a0.normal - a1.normal).length_squared();
+        let normal_weight = (-normal_distance / (self.sigma_normal * self.sigma_normal)).exp();
+
+        let depth_distance = (a0.depth - a1.depth).abs() / a0.depth.max(0.001);
+        let depth_weight = (-depth_distance / self.sigma_depth).exp();
+
+        color_weight * normal_weight * depth_weight
+    }
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Denoiser::new(5, 1.0, 0.5, 0.05)
+    }
+}
+
+const ALBEDO_EPSILON: f32 = 0.001;
+
+fn demodulate(color: Vector3, albedo: Vector3) -> Vector3 {
+    Vector3::new(color.r() / (albedo.r() + ALBEDO_EPSILON),
+                 color.g() / (albedo.g() + ALBEDO_EPSILON),
+                 color.b() / (albedo.b() + ALBEDO_EPSILON))
+}
+
+fn remodulate(irradiance: Vector3, albedo: Vector3) -> Vector3 {
+    Vector3::new(irradiance.r() * (albedo.r() + ALBEDO_EPSILON),
+                 irradiance.g() * (albedo.g() + ALBEDO_EPSILON),
+                 irradiance.b() * (albedo.b() + ALBEDO_EPSILON))
+}
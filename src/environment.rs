@@ -0,0 +1,219 @@
+extern crate image;
+extern crate tdmath;
+
+use tdmath::Vector3;
+use image::hdr::HDRDecoder;
+use export;
+use light::luminance;
+use std::f32::consts::PI;
+use std::fs::File;
+use std::io;
+use std::io::BufReader;
+use std::path::Path;
+
+// Equirectangular HDR image lighting the scene from infinitely far away. The top row of the
+// image is straight up and the centre column faces +x before rotation. Directions are sampled
+// in proportion to the brightness of the image so small bright areas like the sun are found.
+pub struct EnvironmentMap {
+    width: u32,
+    height: u32,
+    pixels: Vec<Vector3>,
+    rotation: f32,
+    // Chance of picking each row, then each pixel within its row
+    rows: Distribution,
+    columns: Vec<Distribution>,
+}
+
+impl EnvironmentMap {
+    // rotation turns the map around the y axis in degrees, intensity scales its radiance
+    pub fn new(width: u32, height: u32, pixels: Vec<Vector3>, rotation: f32, intensity: f32) -> Self {
+        let pixels: Vec<Vector3> = pixels.into_iter().map(|p| p * intensity).collect();
+
+        let mut columns = Vec::with_capacity(height as usize);
+        let mut row_weights = Vec::with_capacity(height as usize);
+        for y in 0..height {
+            // Rows near the poles cover less solid angle
+            let sin_theta = (PI * (y as f32 + 0.5) / height as f32).sin();
+            let row = &pixels[(y * width) as usize..((y + 1) * width) as usize];
+            let weights: Vec<f32> = row.iter().map(|p| luminance(*p).max(0.0) * sin_theta).collect();
+
+            let column = Distribution::new(&weights);
+            row_weights.push(column.total());
+            columns.push(column);
+        }
+
+        EnvironmentMap {
+            width,
+            height,
+            pixels,
+            rotation: rotation.to_radians(),
+            rows: Distribution::new(&row_weights),
+            columns,
+        }
+    }
+
+    // Tabulates a function giving the radiance arriving from each direction
+    pub fn from_radiance<F: Fn(Vector3) -> Vector3>(width: u32, height: u32, radiance: F) -> Self {
+        let mut pixels = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            let theta = PI * (y as f32 + 0.5) / height as f32;
+            for x in 0..width {
+                let phi = 2.0 * PI * ((x as f32 + 0.5) / width as f32 - 0.5);
+                pixels.push(radiance(Vector3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin())));
+            }
+        }
+
+        EnvironmentMap::new(width, height, pixels, 0.0, 1.0)
+    }
+
+    // Loads a .hdr or .exr image
+    pub fn load(path: &Path, rotation: f32, intensity: f32) -> io::Result<Self> {
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+
+        let (width, height, pixels) = if extension == "exr" {
+            export::read_exr(path)?
+        } else {
+            let reader = BufReader::new(File::open(path)?);
+            let decoder = HDRDecoder::new(reader).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}", e)))?;
+            let metadata = decoder.metadata();
+            let image = decoder.read_image_hdr().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}", e)))?;
+            let pixels = image.iter().map(|p| Vector3::new(p.data[0], p.data[1], p.data[2])).collect();
+            (metadata.width, metadata.height, pixels)
+        };
+
+        Ok(EnvironmentMap::new(width, height, pixels, rotation, intensity))
+    }
+
+    pub fn radiance(&self, direction: Vector3) -> Vector3 {
+        let (u, v) = self.direction_to_uv(direction);
+        self.pixels[self.pixel_index(u, v)]
+    }
+
+    // Direction towards the environment and its density over solid angle
+    pub fn sample(&self, sample: (f32, f32)) -> (Vector3, f32) {
+        let (y, v) = self.rows.sample(sample.1);
+        let (_, u) = self.columns[y].sample(sample.0);
+
+        let direction = self.uv_to_direction(u, v);
+        (direction, self.pdf_value(direction))
+    }
+
+    pub fn pdf_value(&self, direction: Vector3) -> f32 {
+        let (u, v) = self.direction_to_uv(direction);
+        let sin_theta = (PI * v).sin();
+        if sin_theta <= 0.0 || self.rows.total() <= 0.0 {
+            return 0.0;
+        }
+
+        let x = ((u * self.width as f32) as usize).min(self.width as usize - 1);
+        let y = ((v * self.height as f32) as usize).min(self.height as usize - 1);
+        let pdf_uv = self.rows.pdf(y) * self.columns[y].pdf(x);
+
+        pdf_uv / (2.0 * PI * PI * sin_theta)
+    }
+
+    // Luminance averaged over the sphere of directions
+    pub fn average_luminance(&self) -> f32 {
+        let mut sum = 0.0;
+        let mut weight = 0.0;
+        for y in 0..self.height {
+            let sin_theta = (PI * (y as f32 + 0.5) / self.height as f32).sin();
+            for x in 0..self.width {
+                sum += luminance(self.pixels[(y * self.width + x) as usize]) * sin_theta;
+                weight += sin_theta;
+            }
+        }
+
+        if weight > 0.0 {
+            sum / weight
+        } else {
+            0.0
+        }
+    }
+
+    fn pixel_index(&self, u: f32, v: f32) -> usize {
+        let x = ((u * self.width as f32) as u32).min(self.width - 1);
+        let y = ((v * self.height as f32) as u32).min(self.height - 1);
+        (y * self.width + x) as usize
+    }
+
+    fn direction_to_uv(&self, direction: Vector3) -> (f32, f32) {
+        let d = direction.normalized();
+        let theta = d.y.max(-1.0).min(1.0).acos();
+        let phi = d.z.atan2(d.x) - self.rotation;
+
+        let u = phi / (2.0 * PI) + 0.5;
+        (u - u.floor(), theta / PI)
+    }
+
+    fn uv_to_direction(&self, u: f32, v: f32) -> Vector3 {
+        let theta = PI * v;
+        let phi = 2.0 * PI * (u - 0.5) + self.rotation;
+
+        Vector3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin())
+    }
+}
+
+// Piecewise constant distribution over [0, 1) with one piece per weight
+struct Distribution {
+    cdf: Vec<f32>,
+    weights: Vec<f32>,
+    total: f32,
+}
+
+impl Distribution {
+    fn new(weights: &[f32]) -> Self {
+        let total: f32 = weights.iter().sum();
+
+        let mut cdf = Vec::with_capacity(weights.len() + 1);
+        cdf.push(0.0);
+        let mut sum = 0.0;
+        for (i, weight) in weights.iter().enumerate() {
+            sum += if total > 0.0 {
+                weight / total
+            } else {
+                1.0 / weights.len() as f32
+            };
+            cdf.push(if i + 1 == weights.len() { 1.0 } else { sum });
+        }
+
+        Distribution {
+            cdf,
+            weights: weights.to_vec(),
+            total,
+        }
+    }
+
+    fn total(&self) -> f32 {
+        self.total
+    }
+
+    // Density of piece index over [0, 1)
+    fn pdf(&self, index: usize) -> f32 {
+        (self.cdf[index + 1] - self.cdf[index]) * self.weights.len() as f32
+    }
+
+    // Returns the piece u falls in and a position within [0, 1) distributed by the weights
+    fn sample(&self, u: f32) -> (usize, f32) {
+        let pieces = self.weights.len();
+        let index = match self.cdf.binary_search_by(|c| c.partial_cmp(&u).unwrap()) {
+            Ok(index) => index,
+            Err(index) => index - 1,
+        };
+
+        // Skip empty pieces that u can land on the edge of
+        let mut index = index.min(pieces - 1);
+        while index < pieces - 1 && self.cdf[index + 1] <= self.cdf[index] {
+            index += 1;
+        }
+
+        let width = self.cdf[index + 1] - self.cdf[index];
+        let offset = if width > 0.0 {
+            (u - self.cdf[index]) / width
+        } else {
+            0.5
+        };
+
+        (index, (index as f32 + offset.max(0.0).min(0.99999994)) / pieces as f32)
+    }
+}
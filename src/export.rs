@@ -0,0 +1,515 @@
+extern crate image;
+extern crate tdmath;
+
+use framebuffer::FrameBuffer;
+use film::Film;
+use tdmath::Vector3;
+use image::{Rgb, RgbImage};
+use image::hdr::HDREncoder;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::io::BufWriter;
+use std::path::Path;
+
+// Saves the frame as linear radiance for .exr and .hdr files, otherwise as a display image
+// using the film's tone mapping
+pub fn save(frame: &FrameBuffer, film: &Film, path: &Path) -> io::Result<()> {
+    let extension = extension(path);
+
+    if extension == "exr" {
+        save_exr(frame, path)
+    } else if extension == "hdr" {
+        save_hdr(frame, path)
+    } else {
+        frame.to_image(film).save(path)
+    }
+}
+
+pub fn save_hdr(frame: &FrameBuffer, path: &Path) -> io::Result<()> {
+    let data: Vec<Rgb<f32>> = frame.pixels().iter().map(|p| {
+        Rgb { data: [p.r().max(0.0), p.g().max(0.0), p.b().max(0.0)] }
+    }).collect();
+
+    let file = BufWriter::new(File::create(path)?);
+    HDREncoder::new(file).encode(&data, frame.width() as usize, frame.height() as usize)
+}
+
+pub fn save_exr(frame: &FrameBuffer, path: &Path) -> io::Result<()> {
+    let r: Vec<f32> = frame.pixels().iter().map(|p| p.r()).collect();
+    let g: Vec<f32> = frame.pixels().iter().map(|p| p.g()).collect();
+    let b: Vec<f32> = frame.pixels().iter().map(|p| p.b()).collect();
+
+    let mut channels = vec![ExrChannel::new("R", r), ExrChannel::new("G", g), ExrChannel::new("B", b)];
+
+    // AOVs are stored as extra layers alongside the beauty pass
+    if let Some(aovs) = frame.aovs() {
+        channels.push(ExrChannel::new("depth.Z", aovs.iter().map(|a| a.depth).collect()));
+        channels.push(ExrChannel::new("normal.X", aovs.iter().map(|a| a.normal.x).collect()));
+        channels.push(ExrChannel::new("normal.Y", aovs.iter().map(|a| a.normal.y).collect()));
+        channels.push(ExrChannel::new("normal.Z", aovs.iter().map(|a| a.normal.z).collect()));
+        channels.push(ExrChannel::new("albedo.R", aovs.iter().map(|a| a.albedo.r()).collect()));
+        channels.push(ExrChannel::new("albedo.G", aovs.iter().map(|a| a.albedo.g()).collect()));
+        channels.push(ExrChannel::new("albedo.B", aovs.iter().map(|a| a.albedo.b()).collect()));
+        channels.push(ExrChannel::new("object_id.id", aovs.iter().map(|a| a.object_id).collect()));
+        channels.push(ExrChannel::new("material_id.id", aovs.iter().map(|a| a.material_id).collect()));
+    }
+
+    write_exr(path, frame.width(), frame.height(), channels)
+}
+
+// Writes each AOV to its own PNG next to path, e.g. output_depth.png. EXR output already
+// contains the AOVs as layers so nothing is written for it.
+pub fn save_aov_images(frame: &FrameBuffer, path: &Path) -> io::Result<()> {
+    let aovs = match frame.aovs() {
+        Some(aovs) => aovs,
+        None => return Ok(()),
+    };
+
+    if extension(path) == "exr" {
+        return Ok(());
+    }
+
+    let aov_path = |name: &str| {
+        let stem = match path.file_stem() {
+            Some(stem) => stem.to_string_lossy().into_owned(),
+            None => String::from("output"),
+        };
+
+        path.with_file_name(format!("{}_{}.png", stem, name))
+    };
+
+    let width = frame.width();
+    let height = frame.height();
+    let max_depth = aovs.iter().fold(0.0f32, |max, a| max.max(a.depth));
+
+    let depth = RgbImage::from_fn(width, height, |x, y| {
+        let a = aovs[(y * width + x) as usize];
+        let d = if max_depth > 0.0 {
+            a.depth / max_depth
+        } else {
+            0.0
+        };
+
+        to_rgb(Vector3::new(d, d, d))
+    });
+    depth.save(aov_path("depth"))?;
+
+    let normal = RgbImage::from_fn(width, height, |x, y| {
+        let a = aovs[(y * width + x) as usize];
+        to_rgb(a.normal * 0.5 + Vector3::new(0.5, 0.5, 0.5))
+    });
+    normal.save(aov_path("normal"))?;
+
+    let albedo = RgbImage::from_fn(width, height, |x, y| {
+        let a = aovs[(y * width + x) as usize];
+        to_rgb(Film::default().map(a.albedo))
+    });
+    albedo.save(aov_path("albedo"))?;
+
+    let object_id = RgbImage::from_fn(width, height, |x, y| {
+        id_to_rgb(aovs[(y * width + x) as usize].object_id)
+    });
+    object_id.save(aov_path("object_id"))?;
+
+    let material_id = RgbImage::from_fn(width, height, |x, y| {
+        id_to_rgb(aovs[(y * width + x) as usize].material_id)
+    });
+    material_id.save(aov_path("material_id"))
+}
+
+fn extension(path: &Path) -> String {
+    match path.extension() {
+        Some(extension) => extension.to_string_lossy().to_lowercase(),
+        None => String::new(),
+    }
+}
+
+fn to_rgb(c: Vector3) -> Rgb<u8> {
+    let r = (255.99 * c.r().max(0.0).min(1.0)) as u8;
+    let g = (255.99 * c.g().max(0.0).min(1.0)) as u8;
+    let b = (255.99 * c.b().max(0.0).min(1.0)) as u8;
+
+    Rgb { data: [r, g, b] }
+}
+
+// Hashes an id to a distinct colour, leaving pixels with no id black
+fn id_to_rgb(id: f32) -> Rgb<u8> {
+    if id < 0.0 {
+        return Rgb { data: [0, 0, 0] };
+    }
+
+    let mut h = (id as u32).wrapping_add(1).wrapping_mul(0x9E37_79B9);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85EB_CA6B);
+    h ^= h >> 13;
+
+    Rgb { data: [h as u8, (h >> 8) as u8, (h >> 16) as u8] }
+}
+
+pub struct ExrChannel {
+    name: String,
+    data: Vec<f32>,
+}
+
+impl ExrChannel {
+    // data holds one value per pixel, row by row starting at the top-left corner
+    pub fn new(name: &str, data: Vec<f32>) -> Self {
+        ExrChannel {
+            name: name.to_owned(),
+            data,
+        }
+    }
+}
+
+// Writes an uncompressed single-part scanline OpenEXR file with 32-bit float channels
+pub fn write_exr(path: &Path, width: u32, height: u32, mut channels: Vec<ExrChannel>) -> io::Result<()> {
+    // Channels must be listed in alphabetical order and pixel data follows the same order
+    channels.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut header = Vec::new();
+
+    // Magic number and version 2 with no flags set
+    write_i32(&mut header, 20000630);
+    write_i32(&mut header, 2);
+
+    let mut chlist = Vec::new();
+    for channel in &channels {
+        chlist.extend_from_slice(channel.name.as_bytes());
+        chlist.push(0);
+        write_i32(&mut chlist, 2); // FLOAT
+        chlist.extend_from_slice(&[0, 0, 0, 0]); // pLinear and reserved
+        write_i32(&mut chlist, 1); // x sampling
+        write_i32(&mut chlist, 1); // y sampling
+    }
+    chlist.push(0);
+    write_attribute(&mut header, "channels", "chlist", &chlist);
+
+    write_attribute(&mut header, "compression", "compression", &[0]);
+
+    let mut window = Vec::new();
+    write_i32(&mut window, 0);
+    write_i32(&mut window, 0);
+    write_i32(&mut window, width as i32 - 1);
+    write_i32(&mut window, height as i32 - 1);
+    write_attribute(&mut header, "dataWindow", "box2i", &window);
+    write_attribute(&mut header, "displayWindow", "box2i", &window);
+
+    write_attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+
+    let mut aspect = Vec::new();
+    write_f32(&mut aspect, 1.0);
+    write_attribute(&mut header, "pixelAspectRatio", "float", &aspect);
+
+    let mut center = Vec::new();
+    write_f32(&mut center, 0.0);
+    write_f32(&mut center, 0.0);
+    write_attribute(&mut header, "screenWindowCenter", "v2f", &center);
+
+    let mut window_width = Vec::new();
+    write_f32(&mut window_width, 1.0);
+    write_attribute(&mut header, "screenWindowWidth", "float", &window_width);
+
+    header.push(0);
+
+    // Each scanline is stored in its own block preceded by its y coordinate and data size
+    let line_size = width as usize * channels.len() * 4;
+    let block_size = 8 + line_size;
+    let table_size = height as usize * 8;
+
+    let mut offsets = Vec::with_capacity(table_size);
+    for y in 0..height as usize {
+        let offset = header.len() + table_size + y * block_size;
+        write_u32(&mut offsets, offset as u32);
+        write_u32(&mut offsets, (offset as u64 >> 32) as u32);
+    }
+
+    let mut file = BufWriter::new(File::create(path)?);
+    file.write_all(&header)?;
+    file.write_all(&offsets)?;
+
+    let mut line = Vec::with_capacity(block_size);
+    for y in 0..height as usize {
+        line.clear();
+        write_i32(&mut line, y as i32);
+        write_i32(&mut line, line_size as i32);
+
+        for channel in &channels {
+            let row = &channel.data[y * width as usize..(y + 1) * width as usize];
+            for &value in row {
+                write_f32(&mut line, value);
+            }
+        }
+
+        file.write_all(&line)?;
+    }
+
+    file.flush()
+}
+
+fn write_attribute(buffer: &mut Vec<u8>, name: &str, attribute_type: &str, value: &[u8]) {
+    buffer.extend_from_slice(name.as_bytes());
+    buffer.push(0);
+    buffer.extend_from_slice(attribute_type.as_bytes());
+    buffer.push(0);
+    write_i32(buffer, value.len() as i32);
+    buffer.extend_from_slice(value);
+}
+
+fn write_u32(buffer: &mut Vec<u8>, value: u32) {
+    buffer.push(value as u8);
+    buffer.push((value >> 8) as u8);
+    buffer.push((value >> 16) as u8);
+    buffer.push((value >> 24) as u8);
+}
+
+fn write_i32(buffer: &mut Vec<u8>, value: i32) {
+    write_u32(buffer, value as u32);
+}
+
+fn write_f32(buffer: &mut Vec<u8>, value: f32) {
+    write_u32(buffer, value.to_bits());
+}
+
+
+// Reads the R, G and B channels of an uncompressed single-part scanline OpenEXR file with half
+// or float channels, as written by write_exr. Pixels are returned row by row from the top.
+pub fn read_exr(path: &Path) -> io::Result<(u32, u32, Vec<Vector3>)> {
+    let mut data = Vec::new();
+    File::open(path)?.read_to_end(&mut data)?;
+
+    if data.len() < 8 || read_u32(&data, 0) != 20000630 {
+        return Err(invalid_exr("not an OpenEXR file"));
+    }
+
+    if read_u32(&data, 4) & 0xffff_ff00 != 0 {
+        return Err(invalid_exr("only single-part scanline files are supported"));
+    }
+
+    // Channel names and sizes in bytes, in file order
+    let mut channels: Vec<(String, usize, i32)> = Vec::new();
+    let mut window = None;
+    let mut offset = 8;
+
+    loop {
+        let name = read_string(&data, &mut offset)?;
+        if name.is_empty() {
+            break;
+        }
+
+        let _attribute_type = read_string(&data, &mut offset)?;
+        let size = read_u32(&data, offset) as usize;
+        offset += 4;
+        if offset + size > data.len() {
+            return Err(invalid_exr("truncated header"));
+        }
+        let value = &data[offset..offset + size];
+        offset += size;
+
+        if name == "channels" {
+            let mut position = 0;
+            loop {
+                let channel = read_string(value, &mut position)?;
+                if channel.is_empty() {
+                    break;
+                }
+
+                let pixel_type = read_u32(value, position) as i32;
+                position += 16;
+                let bytes = if pixel_type == 1 { 2 } else { 4 };
+                channels.push((channel, bytes, pixel_type));
+            }
+        } else if name == "compression" {
+            if value.first() != Some(&0) {
+                return Err(invalid_exr("only uncompressed files are supported"));
+            }
+        } else if name == "dataWindow" {
+            let x0 = read_u32(value, 0) as i32;
+            let y0 = read_u32(value, 4) as i32;
+            let x1 = read_u32(value, 8) as i32;
+            let y1 = read_u32(value, 12) as i32;
+            window = Some((x0, y0, x1, y1));
+        }
+    }
+
+    let (x0, y0, x1, y1) = match window {
+        Some(window) => window,
+        None => return Err(invalid_exr("missing dataWindow")),
+    };
+    if x1 < x0 || y1 < y0 {
+        return Err(invalid_exr("empty dataWindow"));
+    }
+
+    let width = (x1 as i64 - x0 as i64 + 1) as usize;
+    let height = (y1 as i64 - y0 as i64 + 1) as usize;
+
+    // Every scanline has an entry in the offset table, which bounds the size of the image
+    if offset + height * 8 > data.len() {
+        return Err(invalid_exr("truncated offset table"));
+    }
+
+    // Uncompressed files store every scanline in full, so a dataWindow needing more bytes than
+    // the file holds is corrupt. Checked before allocating so it can't ask for huge buffers.
+    let line_size: usize = channels.iter().map(|channel| channel.1 * width).sum();
+    match line_size.checked_mul(height) {
+        Some(size) if line_size <= data.len() && size <= data.len() => {}
+        _ => return Err(invalid_exr("dataWindow larger than the file")),
+    }
+
+    let mut pixels = vec![Vector3::zero(); width * height];
+
+    for line in 0..height {
+        let block = read_u32(&data, offset + line * 8) as usize;
+        if block + 8 + line_size > data.len() {
+            return Err(invalid_exr("truncated scanline"));
+        }
+
+        let y = read_u32(&data, block) as i32 as i64 - y0 as i64;
+        if y < 0 || y >= height as i64 {
+            return Err(invalid_exr("scanline outside dataWindow"));
+        }
+        let y = y as usize;
+        let mut position = block + 8;
+
+        for &(ref name, bytes, pixel_type) in &channels {
+            let component = match name.as_str() {
+                "R" => Some(0),
+                "G" => Some(1),
+                "B" => Some(2),
+                _ => None,
+            };
+
+            if let Some(component) = component {
+                for x in 0..width {
+                    let p = position + x * bytes;
+                    let value = match pixel_type {
+                        1 => half_to_f32(data[p] as u16 | (data[p + 1] as u16) << 8),
+                        2 => f32::from_bits(read_u32(&data, p)),
+                        _ => read_u32(&data, p) as f32,
+                    };
+                    pixels[y * width + x][component] = value;
+                }
+            }
+
+            position += bytes * width;
+        }
+    }
+
+    Ok((width as u32, height as u32, pixels))
+}
+
+fn invalid_exr(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("Invalid EXR file: {}", message))
+}
+
+fn read_u32(buffer: &[u8], offset: usize) -> u32 {
+    if offset + 4 > buffer.len() {
+        return 0;
+    }
+
+    buffer[offset] as u32 | (buffer[offset + 1] as u32) << 8 | (buffer[offset + 2] as u32) << 16 | (buffer[offset + 3] as u32) << 24
+}
+
+fn read_string(buffer: &[u8], offset: &mut usize) -> io::Result<String> {
+    let start = *offset;
+    while *offset < buffer.len() && buffer[*offset] != 0 {
+        *offset += 1;
+    }
+
+    if *offset >= buffer.len() {
+        return Err(invalid_exr("truncated header"));
+    }
+
+    let value = String::from_utf8_lossy(&buffer[start..*offset]).into_owned();
+    *offset += 1;
+    Ok(value)
+}
+
+fn half_to_f32(half: u16) -> f32 {
+    let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((half >> 10) & 0x1f) as i32;
+    let mantissa = (half & 0x3ff) as f32;
+
+    if exponent == 0 {
+        sign * mantissa * 2.0f32.powi(-24)
+    } else if exponent == 31 {
+        if mantissa == 0.0 {
+            sign * ::std::f32::INFINITY
+        } else {
+            ::std::f32::NAN
+        }
+    } else {
+        sign * (1.0 + mantissa / 1024.0) * 2.0f32.powi(exponent - 15)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn write_test_image(name: &str) -> (PathBuf, Vec<Vector3>) {
+        let (width, height) = (3, 2);
+        let pixels: Vec<Vector3> = (0..width * height)
+            .map(|i| Vector3::new(i as f32, 0.5 * i as f32, -0.25 * i as f32))
+            .collect();
+
+        let channels = vec![
+            ExrChannel::new("R", pixels.iter().map(|c| c.x).collect()),
+            ExrChannel::new("G", pixels.iter().map(|c| c.y).collect()),
+            ExrChannel::new("B", pixels.iter().map(|c| c.z).collect()),
+            ExrChannel::new("depth", vec![7.0; width * height]),
+        ];
+
+        let path = env::temp_dir().join(name);
+        write_exr(&path, width as u32, height as u32, channels).unwrap();
+        (path, pixels)
+    }
+
+    #[test]
+    fn read_exr_returns_what_write_exr_wrote() {
+        let (path, pixels) = write_test_image("raytracer_round_trip.exr");
+        let (width, height, read) = read_exr(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!((width, height), (3, 2));
+        for (a, b) in read.iter().zip(pixels.iter()) {
+            assert_eq!((a.x, a.y, a.z), (b.x, b.y, b.z));
+        }
+    }
+
+    #[test]
+    fn read_exr_rejects_scanlines_outside_the_data_window() {
+        let (path, _) = write_test_image("raytracer_bad_scanline.exr");
+        let mut data = fs::read(&path).unwrap();
+
+        // Point the first scanline block at a row below the image
+        let table = data.len() - 2 * (8 + 3 * 4 * 4) - 2 * 8;
+        let block = read_u32(&data, table) as usize;
+        data[block] = 9;
+        fs::write(&path, &data).unwrap();
+
+        let result = read_exr(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn read_exr_rejects_data_windows_larger_than_the_file() {
+        let (path, _) = write_test_image("raytracer_bad_data_window.exr");
+        let mut data = fs::read(&path).unwrap();
+
+        // Widen the dataWindow to 2^31 pixels, its box2i follows the name, type and size
+        let name = b"dataWindow\0box2i\0";
+        let start = data.windows(name.len()).position(|window| window == name).unwrap();
+        let x1 = start + name.len() + 4 + 8;
+        data[x1 + 3] = 0x7f;
+        fs::write(&path, &data).unwrap();
+
+        let result = read_exr(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
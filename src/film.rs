@@ -0,0 +1,157 @@
+extern crate tdmath;
+extern crate toml;
+
+use tdmath::Vector3;
+use toml::Value;
+
+#[derive(Debug, Copy, Clone)]
+pub enum ToneMapper {
+    Clamp,
+    Reinhard,
+    ExtendedReinhard(f32),
+    Aces,
+    Uncharted2,
+}
+
+impl ToneMapper {
+    // white is the radiance mapped to 1.0 by extended Reinhard
+    pub fn from_name(name: &str, white: f32) -> Option<ToneMapper> {
+        match name {
+            "clamp" | "none" => Some(ToneMapper::Clamp),
+            "reinhard" => Some(ToneMapper::Reinhard),
+            "extended_reinhard" => Some(ToneMapper::ExtendedReinhard(white)),
+            "aces" => Some(ToneMapper::Aces),
+            "uncharted2" => Some(ToneMapper::Uncharted2),
+            _ => None,
+        }
+    }
+
+    pub fn map(&self, c: f32) -> f32 {
+        match *self {
+            ToneMapper::Clamp => c,
+            ToneMapper::Reinhard => c / (1.0 + c),
+            ToneMapper::ExtendedReinhard(white) => c * (1.0 + c / (white * white)) / (1.0 + c),
+            ToneMapper::Aces => {
+                // Krzysztof Narkowicz's fit of the ACES filmic curve
+                let x = c * 0.6;
+                (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)
+            },
+            ToneMapper::Uncharted2 => {
+                let exposure_bias = 2.0;
+                let white = 11.2;
+                uncharted2_partial(c * exposure_bias) / uncharted2_partial(white)
+            },
+        }
+    }
+}
+
+// Display transform applied when converting linear radiance to an 8-bit image
+#[derive(Debug, Copy, Clone)]
+pub struct Film {
+    tone_mapper: ToneMapper,
+    exposure: f32,
+    white: f32,
+}
+
+impl Film {
+    // exposure is in stops, each one doubling the brightness of the image
+    pub fn new(tone_mapper: ToneMapper, exposure: f32) -> Self {
+        Film {
+            tone_mapper,
+            exposure,
+            white: 4.0,
+        }
+    }
+
+    pub fn from_toml(film_data: &Value) -> Self {
+        let white = match film_data.get("white") {
+            Some(white) => white.as_float().unwrap() as f32,
+            None => 4.0,
+        };
+
+        let tone_mapper = match film_data.get("tone_mapper") {
+            Some(name) => {
+                let name = name.as_str().unwrap();
+                ToneMapper::from_name(name, white).expect("Unknown tone mapper")
+            },
+            None => ToneMapper::Clamp,
+        };
+
+        let exposure = match film_data.get("exposure") {
+            Some(exposure) => exposure.as_float().unwrap() as f32,
+            None => 0.0,
+        };
+
+        let mut film = Film::new(tone_mapper, exposure);
+        film.set_white(white);
+        film
+    }
+
+    pub fn tone_mapper(&self) -> ToneMapper {
+        self.tone_mapper
+    }
+
+    pub fn exposure(&self) -> f32 {
+        self.exposure
+    }
+
+    // White point of the scene, used when switching to extended Reinhard
+    pub fn white(&self) -> f32 {
+        self.white
+    }
+
+    pub fn set_tone_mapper(&mut self, tone_mapper: ToneMapper) {
+        self.tone_mapper = tone_mapper;
+    }
+
+    pub fn set_exposure(&mut self, exposure: f32) {
+        self.exposure = exposure;
+    }
+
+    pub fn set_white(&mut self, white: f32) {
+        self.white = white;
+    }
+
+    // Maps linear radiance to sRGB encoded values in the range 0 to 1
+    pub fn map(&self, color: Vector3) -> Vector3 {
+        let scale = 2.0f32.powf(self.exposure);
+        let r = srgb_encode(self.tone_mapper.map(color.r() * scale));
+        let g = srgb_encode(self.tone_mapper.map(color.g() * scale));
+        let b = srgb_encode(self.tone_mapper.map(color.b() * scale));
+
+        Vector3::new(r, g, b)
+    }
+}
+
+impl Default for Film {
+    fn default() -> Self {
+        Film::new(ToneMapper::Clamp, 0.0)
+    }
+}
+
+fn uncharted2_partial(x: f32) -> f32 {
+    let a = 0.15;
+    let b = 0.50;
+    let c = 0.10;
+    let d = 0.20;
+    let e = 0.02;
+    let f = 0.30;
+
+    ((x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f)) - e / f
+}
+
+fn srgb_encode(c: f32) -> f32 {
+    let c = if c < 0.0 {
+        0.0
+    } else if c > 1.0 {
+        1.0
+    } else {
+        c
+    };
+
+    if c <= 0.0031308 {
+        12.92 * c
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
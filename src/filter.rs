@@ -0,0 +1,124 @@
+extern crate toml;
+
+use std::f32::consts::PI;
+use toml::Value;
+
+// Pixel reconstruction filter. Each sample is splatted to every pixel whose centre lies within
+// radius pixels of it, weighted by the filter evaluated at the offset to that centre.
+#[derive(Debug, Copy, Clone)]
+pub enum Filter {
+    Box(f32),
+    Gaussian(f32),
+    Mitchell(f32),
+    Lanczos(f32),
+    BlackmanHarris(f32),
+}
+
+impl Filter {
+    // A radius of zero or less selects the default radius for the filter
+    pub fn from_name(name: &str, radius: f32) -> Option<Filter> {
+        let radius_or = |default: f32| {
+            if radius > 0.0 {
+                radius
+            } else {
+                default
+            }
+        };
+
+        match name {
+            "box" => Some(Filter::Box(radius_or(0.5))),
+            "gaussian" => Some(Filter::Gaussian(radius_or(1.5))),
+            "mitchell" => Some(Filter::Mitchell(radius_or(2.0))),
+            "lanczos" => Some(Filter::Lanczos(radius_or(2.0))),
+            "blackman_harris" => Some(Filter::BlackmanHarris(radius_or(2.0))),
+            _ => None,
+        }
+    }
+
+    pub fn from_toml(film_data: &Value) -> Self {
+        let radius = match film_data.get("filter_radius") {
+            Some(radius) => radius.as_float().unwrap() as f32,
+            None => 0.0,
+        };
+
+        match film_data.get("filter") {
+            Some(name) => Filter::from_name(name.as_str().unwrap(), radius).expect("Unknown filter"),
+            None => Filter::default(),
+        }
+    }
+
+    pub fn radius(&self) -> f32 {
+        match *self {
+            Filter::Box(radius) => radius,
+            Filter::Gaussian(radius) => radius,
+            Filter::Mitchell(radius) => radius,
+            Filter::Lanczos(radius) => radius,
+            Filter::BlackmanHarris(radius) => radius,
+        }
+    }
+
+    // The same filter with its support changed to radius pixels
+    pub fn with_radius(&self, radius: f32) -> Filter {
+        match *self {
+            Filter::Box(_) => Filter::Box(radius),
+            Filter::Gaussian(_) => Filter::Gaussian(radius),
+            Filter::Mitchell(_) => Filter::Mitchell(radius),
+            Filter::Lanczos(_) => Filter::Lanczos(radius),
+            Filter::BlackmanHarris(_) => Filter::BlackmanHarris(radius),
+        }
+    }
+
+    // x and y are the offsets in pixels from the sample to the pixel centre
+    pub fn evaluate(&self, x: f32, y: f32) -> f32 {
+        self.evaluate_1d(x) * self.evaluate_1d(y)
+    }
+
+    fn evaluate_1d(&self, x: f32) -> f32 {
+        let radius = self.radius();
+        let x = x.abs();
+        if x >= radius {
+            return 0.0;
+        }
+
+        match *self {
+            Filter::Box(_) => 1.0,
+            Filter::Gaussian(_) => {
+                let alpha = 2.0;
+                ((-alpha * x * x).exp() - (-alpha * radius * radius).exp()).max(0.0)
+            },
+            Filter::Mitchell(_) => mitchell(2.0 * x / radius, 1.0 / 3.0, 1.0 / 3.0),
+            Filter::Lanczos(_) => sinc(x) * sinc(x / radius),
+            Filter::BlackmanHarris(_) => {
+                let t = 0.5 + 0.5 * x / radius;
+                let a0 = 0.35875;
+                let a1 = 0.48829;
+                let a2 = 0.14128;
+                let a3 = 0.01168;
+                a0 - a1 * (2.0 * PI * t).cos() + a2 * (4.0 * PI * t).cos() - a3 * (6.0 * PI * t).cos()
+            },
+        }
+    }
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Filter::Box(0.5)
+    }
+}
+
+// Mitchell-Netravali cubic for x in the range 0 to 2
+fn mitchell(x: f32, b: f32, c: f32) -> f32 {
+    if x < 1.0 {
+        ((12.0 - 9.0 * b - 6.0 * c) * x * x * x + (-18.0 + 12.0 * b + 6.0 * c) * x * x + (6.0 - 2.0 * b)) / 6.0
+    } else {
+        ((-b - 6.0 * c) * x * x * x + (6.0 * b + 30.0 * c) * x * x + (-12.0 * b - 48.0 * c) * x + (8.0 * b + 24.0 * c)) / 6.0
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    if x.abs() < 1e-5 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
@@ -0,0 +1,164 @@
+extern crate tdmath;
+extern crate image;
+
+use tdmath::Vector3;
+use image::{RgbImage, Rgb, GrayImage, Luma};
+use rendertile::RenderTile;
+use film::Film;
+use aov::AovPixel;
+
+// Linear float RGB image. Pixels are stored row by row starting at the top-left corner.
+#[derive(Clone)]
+pub struct FrameBuffer {
+    width: u32,
+    height: u32,
+    pixels: Vec<Vector3>,
+    sample_counts: Vec<u32>,
+    aovs: Option<Vec<AovPixel>>,
+}
+
+impl FrameBuffer {
+    pub fn new(width: u32, height: u32) -> Self {
+        FrameBuffer {
+            width,
+            height,
+            pixels: vec![Vector3::zero(); (width * height) as usize],
+            sample_counts: vec![0; (width * height) as usize],
+            aovs: None,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[Vector3] {
+        &self.pixels
+    }
+
+    pub fn get_pixel(&self, x: u32, y: u32) -> Vector3 {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    pub fn put_pixel(&mut self, x: u32, y: u32, color: Vector3) {
+        self.pixels[(y * self.width + x) as usize] = color;
+    }
+
+    pub fn get_sample_count(&self, x: u32, y: u32) -> u32 {
+        self.sample_counts[(y * self.width + x) as usize]
+    }
+
+    pub fn put_sample_count(&mut self, x: u32, y: u32, count: u32) {
+        self.sample_counts[(y * self.width + x) as usize] = count;
+    }
+
+    // AOVs are only present when enabled on the renderer
+    pub fn aovs(&self) -> Option<&[AovPixel]> {
+        match self.aovs {
+            Some(ref aovs) => Some(aovs),
+            None => None,
+        }
+    }
+
+    pub fn put_aov(&mut self, x: u32, y: u32, aov: AovPixel) {
+        if self.aovs.is_none() {
+            self.aovs = Some(vec![AovPixel::new(); (self.width * self.height) as usize]);
+        }
+
+        let index = (y * self.width + x) as usize;
+        if let Some(ref mut aovs) = self.aovs {
+            aovs[index] = aov;
+        }
+    }
+
+    pub fn add_weighted(&mut self, other: &FrameBuffer, weight: f32) {
+        for (pixel, other) in self.pixels.iter_mut().zip(other.pixels.iter()) {
+            *pixel = *pixel + *other * weight;
+        }
+
+        for (count, other) in self.sample_counts.iter_mut().zip(other.sample_counts.iter()) {
+            *count += *other;
+        }
+
+        // AOVs converge quickly so the first set received is kept
+        if self.aovs.is_none() {
+            self.aovs = other.aovs.clone();
+        }
+    }
+
+    pub fn scaled(&self, scale: f32) -> FrameBuffer {
+        FrameBuffer {
+            width: self.width,
+            height: self.height,
+            pixels: self.pixels.iter().map(|&pixel| pixel * scale).collect(),
+            sample_counts: self.sample_counts.clone(),
+            aovs: self.aovs.clone(),
+        }
+    }
+
+    // Copies the per-pixel sample counts and AOVs of a tile. Colour is splatted separately
+    // since filtered samples reach outside the tile. Tiles are rendered with y pointing up,
+    // so rows are flipped when copied in.
+    pub fn copy_from_tile(&mut self, tile: &RenderTile) {
+        for j in 0..tile.height() {
+            for i in 0..tile.width() {
+                let x = tile.x() + i;
+                let y = self.height - 1 - (tile.y() + j);
+                self.put_sample_count(x, y, tile.get_sample_count(i, j));
+
+                if let Some(aov) = tile.get_aov(i, j) {
+                    self.put_aov(x, y, aov);
+                }
+            }
+        }
+    }
+
+    pub fn to_image(&self, film: &Film) -> RgbImage {
+        let mut image = RgbImage::new(self.width, self.height);
+
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            let col = film.map(self.get_pixel(x, y));
+
+            let ir = clampf(255.99 * col.r(), 0.0, 255.0) as u8;
+            let ig = clampf(255.99 * col.g(), 0.0, 255.0) as u8;
+            let ib = clampf(255.99 * col.b(), 0.0, 255.0) as u8;
+
+            *pixel = Rgb { data: [ir, ig, ib] };
+        }
+
+        image
+    }
+
+    // Greyscale image of the samples taken per pixel, white being the most samples taken
+    pub fn sample_count_image(&self) -> GrayImage {
+        let max = self.sample_counts.iter().cloned().max().unwrap_or(0);
+        let mut image = GrayImage::new(self.width, self.height);
+
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            let count = self.get_sample_count(x, y);
+            let value = if max > 0 {
+                (255.0 * count as f32 / max as f32) as u8
+            } else {
+                0
+            };
+
+            *pixel = Luma { data: [value] };
+        }
+
+        image
+    }
+}
+
+fn clampf(val: f32, min: f32, max: f32) -> f32 {
+    if val < min {
+        min
+    } else if val > max {
+        max
+    } else {
+        val
+    }
+}
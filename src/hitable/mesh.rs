@@ -29,10 +29,11 @@ impl Mesh {
         }
 
         let bvh = BVH::from_list(triangles, 0.0, 0.0);
+        let bounding_box = bvh.bounding_box(0.0, 0.0);
 
         Mesh {
             material,
-            bounding_box: None,
+            bounding_box,
             bvh,
         }
     }
@@ -18,6 +18,8 @@ use tdmath::Ray;
 use tdmath::Vector3;
 use material::Material;
 use aabb::AABB;
+use std::sync::Arc;
+use sampler::Sampler;
 
 pub struct HitRecord<'a> {
     pub t: f32,
@@ -77,9 +79,38 @@ pub trait Hitable: Send + Sync {
         0.0
     }
 
-    fn random(&self, _origin: Vector3) -> Vector3 {
+    fn random(&self, _origin: Vector3, _sampler: &mut Sampler) -> Vector3 {
         Vector3::new(1.0, 0.0, 0.0)
     }
+
+    // Whether random and pdf_value pick directions towards the object. Only objects that can be
+    // sampled become area lights.
+    fn can_sample(&self) -> bool {
+        false
+    }
+}
+
+// Lets an object be shared between the world and an area light
+impl<T: Hitable + ?Sized> Hitable for Arc<T> {
+    fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        (**self).hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
+        (**self).bounding_box(t0, t1)
+    }
+
+    fn pdf_value(&self, origin: Vector3, v: Vector3) -> f32 {
+        (**self).pdf_value(origin, v)
+    }
+
+    fn random(&self, origin: Vector3, sampler: &mut Sampler) -> Vector3 {
+        (**self).random(origin, sampler)
+    }
+
+    fn can_sample(&self) -> bool {
+        (**self).can_sample()
+    }
 }
 
 pub struct FlipNormals {
@@ -113,7 +144,11 @@ impl Hitable for FlipNormals {
         self.hitable.pdf_value(origin, v)
     }
 
-    fn random(&self, origin: Vector3) -> Vector3 {
-        self.hitable.random(origin)
+    fn random(&self, origin: Vector3, sampler: &mut Sampler) -> Vector3 {
+        self.hitable.random(origin, sampler)
+    }
+
+    fn can_sample(&self) -> bool {
+        self.hitable.can_sample()
     }
 }
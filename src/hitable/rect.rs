@@ -1,11 +1,10 @@
 extern crate tdmath;
-extern crate rand;
 
 use hitable::{Hitable, HitRecord};
 use material::Material;
 use aabb::AABB;
 use tdmath::{Vector3, Ray};
-use rand::Rng;
+use sampler::Sampler;
 
 pub struct XYRect {
     x0: f32,
@@ -67,14 +66,18 @@ impl Hitable for XYRect {
         }
     }
 
-    fn random(&self, origin: Vector3) -> Vector3 {
-        let mut rng = rand::thread_rng();
-        let x = self.x0 + rng.gen::<f32>() * (self.x1 - self.x0);
-        let y = self.y0 + rng.gen::<f32>() * (self.y1 - self.y0);
+    fn random(&self, origin: Vector3, sampler: &mut Sampler) -> Vector3 {
+        let (s, t) = sampler.next_2d();
+        let x = self.x0 + s * (self.x1 - self.x0);
+        let y = self.y0 + t * (self.y1 - self.y0);
         let random_point = Vector3::new(x, y, self.k);
 
         random_point - origin
     }
+
+    fn can_sample(&self) -> bool {
+        true
+    }
 }
 
 pub struct XZRect {
@@ -137,14 +140,18 @@ impl Hitable for XZRect {
         }
     }
 
-    fn random(&self, origin: Vector3) -> Vector3 {
-        let mut rng = rand::thread_rng();
-        let x = self.x0 + rng.gen::<f32>() * (self.x1 - self.x0);
-        let z = self.z0 + rng.gen::<f32>() * (self.z1 - self.z0);
+    fn random(&self, origin: Vector3, sampler: &mut Sampler) -> Vector3 {
+        let (s, t) = sampler.next_2d();
+        let x = self.x0 + s * (self.x1 - self.x0);
+        let z = self.z0 + t * (self.z1 - self.z0);
         let random_point = Vector3::new(x, self.k, z);
 
         random_point - origin
     }
+
+    fn can_sample(&self) -> bool {
+        true
+    }
 }
 
 pub struct YZRect {
@@ -207,12 +214,16 @@ impl Hitable for YZRect {
         }
     }
 
-    fn random(&self, origin: Vector3) -> Vector3 {
-        let mut rng = rand::thread_rng();
-        let y = self.y0 + rng.gen::<f32>() * (self.y1 - self.y0);
-        let z = self.z0 + rng.gen::<f32>() * (self.z1 - self.z0);
+    fn random(&self, origin: Vector3, sampler: &mut Sampler) -> Vector3 {
+        let (s, t) = sampler.next_2d();
+        let y = self.y0 + s * (self.y1 - self.y0);
+        let z = self.z0 + t * (self.z1 - self.z0);
         let random_point = Vector3::new(self.k, y, z);
 
         random_point - origin
     }
+
+    fn can_sample(&self) -> bool {
+        true
+    }
 }
\ No newline at end of file
@@ -5,6 +5,7 @@ use tdmath::Vector3;
 use tdmath::Ray;
 use material::Material;
 use aabb::AABB;
+use sampler::{Sampler, sample_to_sphere};
 use std::f32;
 
 pub struct Sphere {
@@ -83,13 +84,17 @@ impl Hitable for Sphere {
         }
     }
 
-    fn random(&self, origin: Vector3) -> Vector3 {
+    fn random(&self, origin: Vector3, sampler: &mut Sampler) -> Vector3 {
         use onb::ONB;
 
         let direction = self.center - origin;
         let distance_squared = direction.length_squared();
         let uvw = ONB::from_w(direction);
-        uvw.local(Vector3::random_to_sphere(self.radius, distance_squared))
+        uvw.local(sample_to_sphere(self.radius, distance_squared, sampler.next_2d()))
+    }
+
+    fn can_sample(&self) -> bool {
+        true
     }
 }
 
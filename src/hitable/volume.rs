@@ -1,12 +1,10 @@
-extern crate rand;
-
 use hitable::{Hitable, HitRecord};
 use material::{Material, ScatterRecord, ScatterType};
 use texture::Texture;
 use tdmath::{Vector3, Ray};
 use aabb::AABB;
-use rand::Rng;
 use std::f32;
+use sampler::{Sampler, sample_in_unit_sphere, hash, to_unit_float};
 
 pub struct ConstantMedium {
     boundary: Box<Hitable>,
@@ -24,6 +22,19 @@ impl ConstantMedium {
     }
 }
 
+impl ConstantMedium {
+    // Hitables have no sampler, so the scatter distance is drawn from a hash of the ray.
+    // Rays are built from sampler values, which keeps seeded renders reproducible.
+    fn ray_random(ray: Ray) -> f32 {
+        let o = ray.origin();
+        let d = ray.direction();
+        let bits = [o.x, o.y, o.z, d.x, d.y, d.z, ray.time()];
+        let h = bits.iter().fold(0, |h, v| hash(h, v.to_bits()));
+
+        to_unit_float(h)
+    }
+}
+
 impl Hitable for ConstantMedium {
     fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
         match self.boundary.hit(ray, -f32::MAX, f32::MAX) {
@@ -45,8 +56,7 @@ impl Hitable for ConstantMedium {
                         }
 
                         let distance_inside_boundary = (hit2.t - hit1.t) * ray.direction().length();
-                        let mut rng = rand::thread_rng();
-                        let hit_distance = -(1.0 / self.density) * rng.gen::<f32>().ln();
+                        let hit_distance = -(1.0 / self.density) * ConstantMedium::ray_random(ray).ln();
                         if hit_distance < distance_inside_boundary {
                             let t = hit1.t + hit_distance / ray.direction().length();
                             let p = ray.point_at_parameter(t);
@@ -82,11 +92,16 @@ impl Isotropic {
 }
 
 impl Material for Isotropic {
-    fn scatter(&self, _ray: Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
-        let scattered = Ray::new(hit_record.p(), Vector3::random_in_unit_sphere(), 0.0);
+    fn scatter(&self, _ray: Ray, hit_record: &HitRecord, sampler: &mut Sampler) -> Option<ScatterRecord> {
+        let direction = sample_in_unit_sphere(sampler.next_2d(), sampler.next_1d());
+        let scattered = Ray::new(hit_record.p(), direction, 0.0);
         let attenuation = self.albedo.value(hit_record.u(), hit_record.v(), hit_record.p());
         let scatter_type = ScatterType::Specular(scattered);
 
         Some(ScatterRecord::new(attenuation, scatter_type))
     }
+
+    fn albedo(&self, hit_record: &HitRecord) -> Vector3 {
+        self.albedo.value(hit_record.u(), hit_record.v(), hit_record.p())
+    }
 }
\ No newline at end of file
@@ -0,0 +1,20 @@
+pub mod pathtracer;
+
+pub use self::pathtracer::PathTracer;
+
+use tdmath::{Vector3, Ray};
+use world::World;
+use light::LightList;
+use sampler::Sampler;
+use aov::AovPixel;
+
+// Computes the radiance arriving along camera rays. lights holds the emitters that can be
+// sampled directly with shadow rays.
+pub trait Integrator: Send + Sync {
+    fn radiance(&self, ray: Ray, world: &World, lights: &LightList, sampler: &mut Sampler) -> Vector3;
+
+    // Like radiance, also returning the AOVs of the first surface the ray hits
+    fn radiance_with_aov(&self, ray: Ray, world: &World, lights: &LightList, sampler: &mut Sampler) -> (Vector3, AovPixel) {
+        (self.radiance(ray, world, lights, sampler), AovPixel::from_ray(ray, world))
+    }
+}
@@ -0,0 +1,231 @@
+use tdmath::{Vector3, Ray};
+use integrator::Integrator;
+use world::World;
+use hitable::{Hitable, HitRecord};
+use light::{Light, LightList, LightSample};
+use material::{ScatterType, SurfaceBSDF, BSDF};
+use roulette::RussianRoulette;
+use sampler::Sampler;
+use aov::AovPixel;
+use std::f32;
+
+// Unidirectional path tracer with next event estimation. At every diffuse bounce a shadow ray
+// is sent towards one of the lights and the bounce direction is drawn from the material. Light
+// reached by either strategy is weighted with the power heuristic so the two combine without
+// counting any light twice.
+pub struct PathTracer {
+    roulette: RussianRoulette,
+}
+
+impl PathTracer {
+    pub fn new(roulette: RussianRoulette) -> Self {
+        PathTracer {
+            roulette,
+        }
+    }
+
+    pub fn roulette(&self) -> RussianRoulette {
+        self.roulette
+    }
+
+    // Light arriving at the surface from one of the lights, without the attenuation
+    fn sample_light(ray: Ray, surface: &Surface, world: &World, lights: &LightList, sampler: &mut Sampler) -> Vector3 {
+        let hit = surface.hit;
+        match lights.sample(hit.p(), sampler) {
+            LightSample::Area { direction, pdf } => {
+                if pdf <= 0.0 || pdf.is_infinite() {
+                    return Vector3::zero();
+                }
+
+                let shadow_ray = Ray::new(hit.p(), direction, ray.time());
+                let scattering = surface.scattering(ray, shadow_ray);
+                if is_black(scattering) {
+                    return Vector3::zero();
+                }
+
+                let emitted = PathTracer::emitted(shadow_ray, world);
+                let bsdf_pdf = surface.pdf_value(ray, direction);
+
+                emitted * scattering * power_heuristic(pdf, bsdf_pdf) / pdf
+            },
+            LightSample::Delta { direction, distance, radiance } => {
+                let shadow_ray = Ray::new(hit.p(), direction, ray.time());
+                let scattering = surface.scattering(ray, shadow_ray);
+                if is_black(scattering) || world.hit(shadow_ray, 0.001, distance).is_some() {
+                    return Vector3::zero();
+                }
+
+                radiance * scattering
+            },
+        }
+    }
+
+    // Light emitted towards the origin of ray by the first thing it hits
+    fn emitted(ray: Ray, world: &World) -> Vector3 {
+        match world.hit(ray, 0.001, f32::MAX) {
+            Some(hit) => hit.material.emit(ray, &hit, hit.u(), hit.v(), hit.p()),
+            None => world.ambient_color_from_ray(ray),
+        }
+    }
+}
+
+impl Default for PathTracer {
+    fn default() -> Self {
+        PathTracer::new(RussianRoulette::default())
+    }
+}
+
+impl Integrator for PathTracer {
+    fn radiance(&self, ray: Ray, world: &World, lights: &LightList, sampler: &mut Sampler) -> Vector3 {
+        self.trace(ray, world, lights, sampler, None)
+    }
+
+    fn radiance_with_aov(&self, ray: Ray, world: &World, lights: &LightList, sampler: &mut Sampler) -> (Vector3, AovPixel) {
+        let mut aov = AovPixel::new();
+        let radiance = self.trace(ray, world, lights, sampler, Some(&mut aov));
+        (radiance, aov)
+    }
+}
+
+impl PathTracer {
+    // Fills aov, when given, from the first hit of the camera ray
+    fn trace(&self, ray: Ray, world: &World, lights: &LightList, sampler: &mut Sampler, mut aov: Option<&mut AovPixel>) -> Vector3 {
+        let mut radiance = Vector3::zero();
+        let mut throughput = Vector3::new(1.0, 1.0, 1.0);
+        let mut ray = ray;
+        let mut depth = 0;
+
+        // Origin and material pdf of the last diffuse bounce. Light found after camera rays
+        // and specular bounces could not have been found by a shadow ray and is not weighted.
+        let mut last_scatter: Option<(Vector3, f32)> = None;
+
+        loop {
+            let hit = match world.hit_object(ray, 0.001, f32::MAX) {
+                Some((hit, object_id)) => {
+                    if let Some(aov) = aov.take() {
+                        *aov = AovPixel::from_hit(ray, &hit, object_id, world);
+                    }
+                    Some(hit)
+                },
+                None => None,
+            };
+
+            let emitted = match hit {
+                Some(ref hit) => hit.material.emit(ray, hit, hit.u(), hit.v(), hit.p()),
+                None => world.ambient_color_from_ray(ray),
+            };
+
+            let weight = match last_scatter {
+                Some((origin, bsdf_pdf)) => power_heuristic(bsdf_pdf, lights.pdf_value(origin, ray.direction())),
+                None => 1.0,
+            };
+
+            radiance = radiance + throughput * emitted * weight;
+
+            let hit = match hit {
+                Some(hit) => hit,
+                None => break,
+            };
+
+            if depth >= self.roulette.max_depth() {
+                break;
+            }
+
+            let scatter = match hit.material.scatter(ray, &hit, sampler) {
+                Some(scatter) => scatter,
+                None => break,
+            };
+
+            let attenuation = scatter.attenuation();
+            let (scattered, weight) = match scatter.scatter_type() {
+                ScatterType::Specular(specular_ray) => {
+                    last_scatter = None;
+                    (specular_ray, attenuation)
+                },
+                ScatterType::Scatter => {
+                    let surface = Surface { hit: &hit, bsdf: scatter.bsdf() };
+
+                    if lights.len() > 0 {
+                        let direct = PathTracer::sample_light(ray, &surface, world, lights, sampler);
+                        radiance = radiance + throughput * attenuation * direct;
+                    }
+
+                    let direction = surface.generate(ray, sampler);
+                    let bsdf_pdf = surface.pdf_value(ray, direction);
+                    if bsdf_pdf <= 0.0 {
+                        break;
+                    }
+
+                    let scattered = Ray::new(hit.p(), direction, ray.time());
+                    let scattering = surface.scattering(ray, scattered);
+                    last_scatter = Some((hit.p(), bsdf_pdf));
+
+                    (scattered, attenuation * scattering / bsdf_pdf)
+                }
+            };
+
+            throughput = throughput * weight;
+
+            let survival = self.roulette.survival_probability(depth, throughput);
+            if survival < 1.0 && sampler.next_1d() >= survival {
+                break;
+            }
+
+            throughput = throughput / survival;
+            ray = scattered;
+            depth += 1;
+        }
+
+        radiance
+    }
+}
+
+// Scattering at a hit, from the BSDF handed over by the material when there is one so its
+// parameters are only looked up once per hit
+struct Surface<'a, 'b: 'a> {
+    hit: &'a HitRecord<'b>,
+    bsdf: Option<&'a SurfaceBSDF>,
+}
+
+impl<'a, 'b> Surface<'a, 'b> {
+    fn scattering(&self, ray: Ray, scattered: Ray) -> Vector3 {
+        match self.bsdf {
+            Some(bsdf) => bsdf.scattering(ray, self.hit, scattered),
+            None => self.hit.material.scattering(ray, self.hit, scattered),
+        }
+    }
+
+    fn generate(&self, ray: Ray, sampler: &mut Sampler) -> Vector3 {
+        match self.bsdf {
+            Some(bsdf) => bsdf.generate(ray, self.hit, sampler),
+            None => self.hit.material.generate(ray, self.hit, sampler),
+        }
+    }
+
+    fn pdf_value(&self, ray: Ray, direction: Vector3) -> f32 {
+        match self.bsdf {
+            Some(bsdf) => bsdf.pdf_value(ray, self.hit, direction),
+            None => self.hit.material.pdf_value(ray, self.hit, direction),
+        }
+    }
+}
+
+// Weight of a sample drawn with density pdf when another strategy could have drawn it with other_pdf
+fn power_heuristic(pdf: f32, other_pdf: f32) -> f32 {
+    let a = pdf * pdf;
+    let b = other_pdf * other_pdf;
+
+    if a.is_infinite() {
+        1.0
+    } else if b.is_infinite() {
+        0.0
+    } else if a + b > 0.0 {
+        a / (a + b)
+    } else {
+        0.0
+    }
+}
+
+fn is_black(color: Vector3) -> bool {
+    color.x <= 0.0 && color.y <= 0.0 && color.z <= 0.0
+}
@@ -0,0 +1,51 @@
+extern crate rand;
+extern crate tdmath;
+extern crate image;
+extern crate toml;
+extern crate threadpool;
+extern crate modelloader;
+
+pub mod hitable;
+pub mod world;
+pub mod camera;
+pub mod material;
+pub mod aabb;
+pub mod bvh;
+pub mod texture;
+pub mod noise;
+pub mod transform;
+pub mod rendertile;
+pub mod framebuffer;
+pub mod renderer;
+pub mod adaptive;
+pub mod export;
+pub mod film;
+pub mod aov;
+pub mod denoise;
+pub mod filter;
+pub mod onb;
+pub mod pdf;
+pub mod sampler;
+pub mod roulette;
+pub mod integrator;
+pub mod light;
+pub mod environment;
+pub mod sky;
+
+pub use tdmath::{Vector3, Ray};
+pub use hitable::{Hitable, HitRecord};
+pub use material::{Material, ScatterRecord, ScatterType, SurfaceBSDF, BSDF};
+pub use texture::Texture;
+pub use world::World;
+pub use camera::Camera;
+pub use framebuffer::FrameBuffer;
+pub use renderer::{Renderer, PassSettings, render_tile};
+pub use adaptive::AdaptiveSampling;
+pub use film::{Film, ToneMapper};
+pub use aov::AovPixel;
+pub use denoise::Denoiser;
+pub use filter::Filter;
+pub use sampler::Sampler;
+pub use roulette::RussianRoulette;
+pub use integrator::{Integrator, PathTracer};
+pub use light::{Light, LightList};
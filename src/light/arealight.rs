@@ -0,0 +1,47 @@
+use tdmath::Vector3;
+use hitable::Hitable;
+use light::{Light, LightSample, luminance};
+use sampler::Sampler;
+use std::f32;
+use std::sync::Arc;
+
+// Geometry with an emissive material, shared with the world. The radiance itself is found by
+// tracing the shadow ray, the light only picks directions towards its geometry.
+pub struct AreaLight {
+    hitable: Arc<Hitable>,
+    power: f32,
+}
+
+impl AreaLight {
+    // emission is the average radiance leaving the surface
+    pub fn new(hitable: Arc<Hitable>, emission: Vector3) -> Self {
+        // Half the surface area of the bounding box approximates the area of flat lights and
+        // stays within a small factor of it for other shapes, which is enough for picking lights
+        let area = match hitable.bounding_box(0.0, 1.0) {
+            Some(bbox) => bbox.surface_area() / 2.0,
+            None => 1.0,
+        };
+
+        AreaLight {
+            hitable,
+            power: luminance(emission).max(0.0) * area * f32::consts::PI,
+        }
+    }
+}
+
+impl Light for AreaLight {
+    fn sample(&self, origin: Vector3, sampler: &mut Sampler) -> LightSample {
+        let direction = self.hitable.random(origin, sampler);
+        let pdf = self.hitable.pdf_value(origin, direction);
+
+        LightSample::Area { direction, pdf }
+    }
+
+    fn pdf_value(&self, origin: Vector3, direction: Vector3) -> f32 {
+        self.hitable.pdf_value(origin, direction)
+    }
+
+    fn power(&self) -> f32 {
+        self.power
+    }
+}
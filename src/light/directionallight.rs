@@ -0,0 +1,41 @@
+use tdmath::Vector3;
+use light::{Light, LightSample, luminance};
+use sampler::Sampler;
+use std::f32;
+
+// Light from infinitely far away arriving from a single direction, like the sun
+pub struct DirectionalLight {
+    direction: Vector3,
+    irradiance: Vector3,
+    scene_radius: f32,
+}
+
+impl DirectionalLight {
+    // direction is the way the light travels. scene_radius bounds the scene and is only used
+    // to estimate how much power the light delivers.
+    pub fn new(direction: Vector3, irradiance: Vector3, scene_radius: f32) -> Self {
+        DirectionalLight {
+            direction: direction.normalized(),
+            irradiance,
+            scene_radius,
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample(&self, _origin: Vector3, _sampler: &mut Sampler) -> LightSample {
+        LightSample::Delta {
+            direction: -self.direction,
+            distance: f32::MAX,
+            radiance: self.irradiance,
+        }
+    }
+
+    fn pdf_value(&self, _origin: Vector3, _direction: Vector3) -> f32 {
+        0.0
+    }
+
+    fn power(&self) -> f32 {
+        f32::consts::PI * self.scene_radius * self.scene_radius * luminance(self.irradiance).max(0.0)
+    }
+}
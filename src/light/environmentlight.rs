@@ -0,0 +1,40 @@
+use tdmath::Vector3;
+use environment::EnvironmentMap;
+use light::{Light, LightSample};
+use sampler::Sampler;
+use std::sync::Arc;
+use std::f32;
+
+// Samples directions towards the bright parts of the world's environment map
+pub struct EnvironmentLight {
+    map: Arc<EnvironmentMap>,
+    power: f32,
+}
+
+impl EnvironmentLight {
+    // scene_radius bounds the scene and is only used to estimate the power reaching it
+    pub fn new(map: Arc<EnvironmentMap>, scene_radius: f32) -> Self {
+        let power = f32::consts::PI * f32::consts::PI * scene_radius * scene_radius * map.average_luminance().max(0.0);
+
+        EnvironmentLight {
+            map,
+            power,
+        }
+    }
+}
+
+impl Light for EnvironmentLight {
+    fn sample(&self, _origin: Vector3, sampler: &mut Sampler) -> LightSample {
+        let (direction, pdf) = self.map.sample(sampler.next_2d());
+
+        LightSample::Area { direction, pdf }
+    }
+
+    fn pdf_value(&self, _origin: Vector3, direction: Vector3) -> f32 {
+        self.map.pdf_value(direction)
+    }
+
+    fn power(&self) -> f32 {
+        self.power
+    }
+}
@@ -0,0 +1,105 @@
+use tdmath::Vector3;
+use light::{Light, LightSample};
+use sampler::Sampler;
+
+// All the lights of a scene. Lights are picked in proportion to their power, so a few bright
+// lights get most of the shadow rays in a scene full of dim ones.
+pub struct LightList {
+    lights: Vec<Box<Light>>,
+    cdf: Vec<f32>,
+}
+
+impl LightList {
+    pub fn new(lights: Vec<Box<Light>>) -> Self {
+        let total: f32 = lights.iter().map(|light| light.power()).sum();
+
+        // Fall back to picking uniformly when no light reports any power
+        let weights: Vec<f32> = if total > 0.0 {
+            lights.iter().map(|light| light.power() / total).collect()
+        } else {
+            lights.iter().map(|_| 1.0 / lights.len() as f32).collect()
+        };
+
+        let mut cdf = Vec::with_capacity(lights.len());
+        let mut sum = 0.0;
+        for weight in weights {
+            sum += weight;
+            cdf.push(sum);
+        }
+
+        LightList {
+            lights,
+            cdf,
+        }
+    }
+
+    pub fn empty() -> Self {
+        LightList::new(Vec::new())
+    }
+
+    pub fn len(&self) -> usize {
+        self.lights.len()
+    }
+
+    // Chance of picking light index
+    pub fn probability(&self, index: usize) -> f32 {
+        if index == 0 {
+            self.cdf[0]
+        } else {
+            self.cdf[index] - self.cdf[index - 1]
+        }
+    }
+
+    fn pick(&self, u: f32) -> usize {
+        let total = self.cdf[self.cdf.len() - 1];
+        let target = u * total;
+        let index = match self.cdf.binary_search_by(|c| c.partial_cmp(&target).unwrap()) {
+            Ok(index) => index + 1,
+            Err(index) => index,
+        };
+
+        // Skip lights that can never be picked, which can end up under target through rounding
+        let mut index = index.min(self.lights.len() - 1);
+        while index < self.lights.len() - 1 && self.probability(index) <= 0.0 {
+            index += 1;
+        }
+
+        index
+    }
+}
+
+impl Light for LightList {
+    // Area samples get the density of the direction over all lights, since a shadow ray picks
+    // up light from whichever emitter it hits. Delta samples are divided by the chance of
+    // picking their light.
+    fn sample(&self, origin: Vector3, sampler: &mut Sampler) -> LightSample {
+        let index = self.pick(sampler.next_1d());
+
+        match self.lights[index].sample(origin, sampler) {
+            LightSample::Area { direction, .. } => {
+                let pdf = self.pdf_value(origin, direction);
+                LightSample::Area { direction, pdf }
+            },
+            LightSample::Delta { direction, distance, radiance } => {
+                let radiance = radiance / self.probability(index);
+                LightSample::Delta { direction, distance, radiance }
+            },
+        }
+    }
+
+    fn pdf_value(&self, origin: Vector3, direction: Vector3) -> f32 {
+        let mut sum = 0.0;
+        for (index, light) in self.lights.iter().enumerate() {
+            let probability = self.probability(index);
+            if probability > 0.0 {
+                sum += probability * light.pdf_value(origin, direction);
+            }
+        }
+
+        sum
+    }
+
+    fn power(&self) -> f32 {
+        self.lights.iter().map(|light| light.power()).sum()
+    }
+}
@@ -0,0 +1,43 @@
+pub mod arealight;
+pub mod pointlight;
+pub mod spotlight;
+pub mod directionallight;
+pub mod environmentlight;
+pub mod sunlight;
+pub mod lightlist;
+
+pub use self::arealight::AreaLight;
+pub use self::pointlight::PointLight;
+pub use self::spotlight::SpotLight;
+pub use self::directionallight::DirectionalLight;
+pub use self::environmentlight::EnvironmentLight;
+pub use self::sunlight::SunLight;
+pub use self::lightlist::LightList;
+
+use tdmath::Vector3;
+use sampler::Sampler;
+
+pub enum LightSample {
+    // Direction towards a light's geometry drawn with density pdf. The light arriving is
+    // whatever emitter a ray in that direction hits first.
+    Area { direction: Vector3, pdf: f32 },
+
+    // Light from a single point or direction, arriving unless something is hit within distance
+    // along the normalized direction. These can't be found by rays scattered from surfaces.
+    Delta { direction: Vector3, distance: f32, radiance: Vector3 },
+}
+
+// Something that emits light and can be sampled directly by shadow rays
+pub trait Light: Send + Sync {
+    fn sample(&self, origin: Vector3, sampler: &mut Sampler) -> LightSample;
+
+    // Density of Area samples in direction, always zero for delta lights
+    fn pdf_value(&self, origin: Vector3, direction: Vector3) -> f32;
+
+    // Emitted power in any consistent unit, lights are picked in proportion to it
+    fn power(&self) -> f32;
+}
+
+pub fn luminance(color: Vector3) -> f32 {
+    0.2126 * color.r() + 0.7152 * color.g() + 0.0722 * color.b()
+}
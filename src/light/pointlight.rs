@@ -0,0 +1,42 @@
+use tdmath::Vector3;
+use light::{Light, LightSample, luminance};
+use sampler::Sampler;
+use std::f32;
+
+// Light radiating equally in all directions from a point
+pub struct PointLight {
+    position: Vector3,
+    intensity: Vector3,
+}
+
+impl PointLight {
+    // intensity is the radiant intensity, the light arriving one unit away
+    pub fn new(position: Vector3, intensity: Vector3) -> Self {
+        PointLight {
+            position,
+            intensity,
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn sample(&self, origin: Vector3, _sampler: &mut Sampler) -> LightSample {
+        let to_light = self.position - origin;
+        let distance_squared = to_light.length_squared();
+        let distance = distance_squared.sqrt();
+
+        LightSample::Delta {
+            direction: to_light / distance,
+            distance,
+            radiance: self.intensity / distance_squared,
+        }
+    }
+
+    fn pdf_value(&self, _origin: Vector3, _direction: Vector3) -> f32 {
+        0.0
+    }
+
+    fn power(&self) -> f32 {
+        4.0 * f32::consts::PI * luminance(self.intensity).max(0.0)
+    }
+}
@@ -0,0 +1,65 @@
+use tdmath::Vector3;
+use light::{Light, LightSample, luminance};
+use sampler::Sampler;
+use std::f32;
+
+// Point light shining into a cone. Intensity is full up to falloff_angle from the axis and fades
+// smoothly to nothing at cone_angle.
+pub struct SpotLight {
+    position: Vector3,
+    axis: Vector3,
+    intensity: Vector3,
+    cos_cone: f32,
+    cos_falloff: f32,
+}
+
+impl SpotLight {
+    // Angles are in degrees from the axis
+    pub fn new(position: Vector3, look_at: Vector3, intensity: Vector3, cone_angle: f32, falloff_angle: f32) -> Self {
+        let falloff_angle = falloff_angle.min(cone_angle);
+
+        SpotLight {
+            position,
+            axis: (look_at - position).normalized(),
+            intensity,
+            cos_cone: cone_angle.to_radians().cos(),
+            cos_falloff: falloff_angle.to_radians().cos(),
+        }
+    }
+
+    fn falloff(&self, cos_theta: f32) -> f32 {
+        if cos_theta <= self.cos_cone {
+            0.0
+        } else if cos_theta >= self.cos_falloff {
+            1.0
+        } else {
+            let t = (cos_theta - self.cos_cone) / (self.cos_falloff - self.cos_cone);
+            t * t * (3.0 - 2.0 * t)
+        }
+    }
+}
+
+impl Light for SpotLight {
+    fn sample(&self, origin: Vector3, _sampler: &mut Sampler) -> LightSample {
+        let to_light = self.position - origin;
+        let distance_squared = to_light.length_squared();
+        let distance = distance_squared.sqrt();
+        let direction = to_light / distance;
+        let falloff = self.falloff(Vector3::dot(-direction, self.axis));
+
+        LightSample::Delta {
+            direction,
+            distance,
+            radiance: self.intensity * falloff / distance_squared,
+        }
+    }
+
+    fn pdf_value(&self, _origin: Vector3, _direction: Vector3) -> f32 {
+        0.0
+    }
+
+    fn power(&self) -> f32 {
+        let solid_angle = 2.0 * f32::consts::PI * (1.0 - 0.5 * (self.cos_falloff + self.cos_cone));
+        solid_angle * luminance(self.intensity).max(0.0)
+    }
+}
@@ -0,0 +1,52 @@
+use tdmath::Vector3;
+use light::{Light, LightSample, luminance};
+use sampler::{Sampler, sample_to_sphere};
+use onb::ONB;
+use std::f32;
+
+// Disk of the sun seen from infinitely far away. Unlike a directional light it has a size, so
+// scattered rays can hit it and shadows have soft edges.
+pub struct SunLight {
+    direction: Vector3,
+    radiance: Vector3,
+    angular_radius: f32,
+    cos_angular_radius: f32,
+    // Written as 4 pi sin^2(r / 2) since 1 - cos(r) loses most of its precision for small disks
+    solid_angle: f32,
+    scene_radius: f32,
+}
+
+impl SunLight {
+    // direction points towards the sun, angular_radius is in radians
+    pub fn new(direction: Vector3, radiance: Vector3, angular_radius: f32, scene_radius: f32) -> Self {
+        SunLight {
+            direction: direction.normalized(),
+            radiance,
+            angular_radius,
+            cos_angular_radius: angular_radius.cos(),
+            solid_angle: 4.0 * f32::consts::PI * (0.5 * angular_radius).sin().powi(2),
+            scene_radius,
+        }
+    }
+}
+
+impl Light for SunLight {
+    fn sample(&self, _origin: Vector3, sampler: &mut Sampler) -> LightSample {
+        let local = sample_to_sphere(self.angular_radius.sin(), 1.0, sampler.next_2d());
+        let direction = ONB::from_w(self.direction).local(local);
+
+        LightSample::Area { direction, pdf: 1.0 / self.solid_angle }
+    }
+
+    fn pdf_value(&self, _origin: Vector3, direction: Vector3) -> f32 {
+        if Vector3::dot(direction.normalized(), self.direction) >= self.cos_angular_radius {
+            1.0 / self.solid_angle
+        } else {
+            0.0
+        }
+    }
+
+    fn power(&self) -> f32 {
+        f32::consts::PI * self.scene_radius * self.scene_radius * luminance(self.radiance).max(0.0) * self.solid_angle
+    }
+}
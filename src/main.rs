@@ -1,44 +1,18 @@
+extern crate raytracer;
 extern crate rand;
-extern crate tdmath;
 extern crate cmdpro;
-extern crate image;
 extern crate toml;
-extern crate threadpool;
-extern crate modelloader;
-
-mod hitable;
-mod world;
-mod camera;
-mod material;
-mod aabb;
-mod bvh;
-mod texture;
-mod noise;
+
 mod settings;
-mod transform;
-mod rendertile;
-mod onb;
-mod pdf;
-
-use tdmath::{Vector3, Ray};
-use hitable::Hitable;
-use world::World;
-use camera::Camera;
-use rand::Rng;
+
+use raytracer::{Renderer, AdaptiveSampling, Film, ToneMapper, Denoiser, Filter};
+use raytracer::{export, sampler};
 use std::time::{SystemTime};
 use cmdpro::{CommandLineProcessor, ParameterType};
 use settings::Settings;
-use image::{Rgba, GenericImage, DynamicImage};
 use std::fs::{File};
 use std::io::prelude::*;
 use toml::Value;
-use rendertile::RenderTile;
-use threadpool::ThreadPool;
-use std::sync::mpsc::channel;
-use std::sync::mpsc::{Sender, Receiver};
-use std::sync::Arc;
-use pdf::*;
-use material::ScatterType;
 
 fn main() {
     let mut command_line_processor = CommandLineProcessor::new();
@@ -48,6 +22,29 @@ fn main() {
     command_line_processor.add_parameter("output", ParameterType::Path, vec!["--output".to_owned(), "-o".to_owned()]);
     command_line_processor.add_parameter("scene", ParameterType::Path, vec!["--scene".to_owned(), "-S".to_owned()]);
     command_line_processor.add_parameter("threads", ParameterType::UInteger, vec!["--threads".to_owned(), "-t".to_owned()]);
+    command_line_processor.add_parameter("tile_size", ParameterType::UInteger, vec!["--tile-size".to_owned()]);
+    command_line_processor.add_parameter("progressive", ParameterType::Bool, vec!["--progressive".to_owned(), "-p".to_owned()]);
+    command_line_processor.add_parameter("pass_samples", ParameterType::UInteger, vec!["--pass-samples".to_owned()]);
+    command_line_processor.add_parameter("preview", ParameterType::Path, vec!["--preview".to_owned()]);
+    command_line_processor.add_parameter("preview_passes", ParameterType::UInteger, vec!["--preview-passes".to_owned()]);
+    command_line_processor.add_parameter("preview_seconds", ParameterType::UInteger, vec!["--preview-seconds".to_owned()]);
+    command_line_processor.add_parameter("adaptive", ParameterType::Float, vec!["--adaptive".to_owned(), "-a".to_owned()]);
+    command_line_processor.add_parameter("min_samples", ParameterType::UInteger, vec!["--min-samples".to_owned()]);
+    command_line_processor.add_parameter("sample_map", ParameterType::Path, vec!["--sample-map".to_owned()]);
+    command_line_processor.add_parameter("tone_mapper", ParameterType::String, vec!["--tone-mapper".to_owned()]);
+    command_line_processor.add_parameter("exposure", ParameterType::Float, vec!["--exposure".to_owned(), "-e".to_owned()]);
+    command_line_processor.add_parameter("aovs", ParameterType::Bool, vec!["--aovs".to_owned()]);
+    command_line_processor.add_parameter("denoise", ParameterType::Bool, vec!["--denoise".to_owned(), "-d".to_owned()]);
+    command_line_processor.add_parameter("denoise_iterations", ParameterType::UInteger, vec!["--denoise-iterations".to_owned()]);
+    command_line_processor.add_parameter("denoise_sigma_color", ParameterType::Float, vec!["--denoise-sigma-color".to_owned()]);
+    command_line_processor.add_parameter("denoise_sigma_normal", ParameterType::Float, vec!["--denoise-sigma-normal".to_owned()]);
+    command_line_processor.add_parameter("denoise_sigma_depth", ParameterType::Float, vec!["--denoise-sigma-depth".to_owned()]);
+    command_line_processor.add_parameter("filter", ParameterType::String, vec!["--filter".to_owned()]);
+    command_line_processor.add_parameter("filter_radius", ParameterType::Float, vec!["--filter-radius".to_owned()]);
+    command_line_processor.add_parameter("sampler", ParameterType::String, vec!["--sampler".to_owned()]);
+    command_line_processor.add_parameter("seed", ParameterType::UInteger, vec!["--seed".to_owned()]);
+    command_line_processor.add_parameter("max_depth", ParameterType::UInteger, vec!["--max-depth".to_owned()]);
+    command_line_processor.add_parameter("rr_start_depth", ParameterType::UInteger, vec!["--rr-start-depth".to_owned()]);
     command_line_processor.set_help_text(include_str!("help.txt"));
 
     command_line_processor.parse_command_line();
@@ -62,134 +59,138 @@ fn main() {
     scene.read_to_string(&mut scene_buffer).expect("Unable to read scene file");
     let scene = scene_buffer.parse::<Value>().expect("Unable to parse scene file");
 
-    let now = SystemTime::now();
-    let nx = settings.width();
-    let ny = settings.height();
-    let ns = settings.samples();
-
-    let world = Arc::new(World::from_toml(&scene));
-    let sample_world = Arc::new(World::from_toml_samples(&scene));
-    let camera = Camera::from_toml(&scene["camera"], nx as f32 / ny as f32);
-
-    let mut tiles = Vec::new();
-    tiles.push(RenderTile::new(0, 0, nx / 2, ny / 2));
-    tiles.push(RenderTile::new(nx / 2, 0, nx / 2, ny / 2));
-    tiles.push(RenderTile::new(0, ny / 2, nx / 2, ny / 2));
-    tiles.push(RenderTile::new(nx / 2, ny / 2, nx / 2, ny / 2));
-
-    let pool = ThreadPool::new(settings.threads() as usize);
-    let (tx, rx): (Sender<RenderTile>, Receiver<RenderTile>) = channel();
-
-    // Render tiles
-    for mut tile in tiles {
-        let tx = tx.clone();
-        let world = Arc::clone(&world);
-        let sample_world = Arc::clone(&sample_world);
-
-        pool.execute(move || {
-            render_tile(&mut tile, &camera, world, sample_world, nx, ny, ns);
-            tx.send(tile).expect("Unable to send data");
-        });
+    let mut film = match scene.get("film") {
+        Some(film_data) => Film::from_toml(film_data),
+        None => Film::default(),
+    };
+
+    if let Some(name) = settings.tone_mapper() {
+        let tone_mapper = ToneMapper::from_name(name, film.white()).expect("Unknown tone mapper");
+        film.set_tone_mapper(tone_mapper);
     }
-    drop(tx);
 
-    // Draw tiles to result
-    let mut image = DynamicImage::new_rgb8(nx, ny);
-    for tile in rx.iter() {
-        image.copy_from(&*tile.image, tile.x(), tile.y());
+    if let Some(exposure) = settings.exposure() {
+        film.set_exposure(exposure);
     }
 
-    match now.elapsed() {
-        Ok(t) => println!("Took {} seconds to render", t.as_secs()),
-        Err(e) => println!("Unable to determine render time: {}", e),
+    let now = SystemTime::now();
+
+    let seed = match settings.seed() {
+        Some(seed) => seed,
+        None => rand::random(),
+    };
+
+    let mut renderer = Renderer::from_toml(&scene, settings.width(), settings.height(), seed);
+    renderer.set_samples(settings.samples());
+    renderer.set_threads(settings.threads());
+    renderer.set_tile_size(settings.tile_size());
+
+    // Path length comes from the scene's [render] table unless overridden
+    let mut roulette = renderer.roulette();
+
+    if let Some(max_depth) = settings.max_depth() {
+        roulette.set_max_depth(max_depth);
     }
 
-    let image = image.flipv();
-    image.save(settings.export_path()).unwrap();
-}
+    if let Some(rr_start_depth) = settings.rr_start_depth() {
+        roulette.set_start_depth(rr_start_depth);
+    }
+    renderer.set_roulette(roulette);
 
-fn render_tile(tile: &mut RenderTile, camera: &Camera, world: Arc<World>, sample_world: Arc<World>, image_width: u32, image_height: u32, samples: u32) {
-    let x = tile.x();
-    let y = tile.y();
-    let x_end = x + tile.width();
-    let y_end = y + tile.height();
-    let mut rng = rand::thread_rng();
+    if let Some(name) = settings.sampler() {
+        let sampler = sampler::from_name(name, seed).expect("Unknown sampler");
+        renderer.set_sampler(sampler);
+    }
 
-    let w: &World = &*world;
-    let s: &Hitable = &*sample_world;
+    if let Some(name) = settings.filter() {
+        let filter = Filter::from_name(name, settings.filter_radius()).expect("Unknown filter");
+        renderer.set_filter(filter);
+    } else if settings.filter_radius() > 0.0 {
+        // Resize the filter from the scene's [film] table, or the default one
+        let filter = renderer.filter().with_radius(settings.filter_radius());
+        renderer.set_filter(filter);
+    }
 
-    for j in y..y_end {
-        for i in x..x_end {
-            let mut col = Vector3::zero();
-            for _ in 0..samples {
-                let u = (i as f32 + rng.gen::<f32>()) / image_width as f32;
-                let v = (j as f32 + rng.gen::<f32>()) / image_height as f32;
+    // The denoiser is guided by the normal, albedo and depth AOVs
+    renderer.set_aovs(settings.aovs() || settings.denoise());
+    let mut denoiser = match scene.get("film") {
+        Some(film_data) => Denoiser::from_toml(film_data),
+        None => Denoiser::default(),
+    };
 
-                let r = camera.get_ray(u, v);
-                let c = color(r, w, s, 0);
+    if let Some(iterations) = settings.denoise_iterations() {
+        denoiser.set_iterations(iterations);
+    }
 
-                if !c.has_nans() {
-                    col = col + c;
-                }
-            }
-            col = col / samples as f32;
-            col = Vector3::new(col.r().sqrt(), col.g().sqrt(), col.b().sqrt());
+    if let Some(sigma) = settings.denoise_sigma_color() {
+        denoiser.set_sigma_color(sigma);
+    }
 
-            let ir = clampf(255.99 * col.r(), 0.0, 255.0) as u8;
-            let ig = clampf(255.99 * col.g(), 0.0, 255.0) as u8;
-            let ib = clampf(255.99 * col.b(), 0.0, 255.0) as u8;
+    if let Some(sigma) = settings.denoise_sigma_normal() {
+        denoiser.set_sigma_normal(sigma);
+    }
 
-            tile.image.put_pixel(i - x, j - y, Rgba { data: [ir, ig, ib, 255] })
-        }
+    if let Some(sigma) = settings.denoise_sigma_depth() {
+        denoiser.set_sigma_depth(sigma);
     }
-}
 
-fn color<'a>(ray: Ray, world: &'a World, sample_world: &'a Hitable, depth: i32) -> Vector3 {
-    match world.hit(ray, 0.001, std::f32::MAX) {
-        Some(hit) => {
-            let emitted = hit.material.emit(ray, &hit, hit.u(), hit.v(), hit.p());
-            if depth < 50 {
-                match hit.material.scatter(ray, &hit) {
-                    Some(scatter) => {
-                        let attenuation = scatter.attenuation();
-
-                        match scatter.scatter_type() {
-                            ScatterType::Specular(specular_ray) => return attenuation * color(specular_ray, world, sample_world, depth+1),
-                            ScatterType::Scatter(pdf) => {
-                                let (scattered, pdf_val, scattering_pdf) = {
-                                    let p_importance = HitablePDF::new(hit.p(), sample_world);
-                                    let p = MixturePDF::new(&p_importance, &*pdf);
-
-                                    let scattered = Ray::new(hit.p(), p.generate(), ray.time());
-                                    let pdf_val = p.value(scattered.direction());
-
-                                    let scattering_pdf = hit.material.scattering_pdf(ray, &hit, scattered);
-
-                                    (scattered, pdf_val, scattering_pdf)
-                                };
-
-                                return emitted + attenuation * scattering_pdf * color(scattered, world, sample_world, depth+1) / pdf_val;
-                            }
-                        }
-                    },
-                    None => return emitted,
+    if settings.adaptive_threshold() > 0.0 && settings.progressive() {
+        println!("Adaptive sampling is not supported with --progressive, taking {} samples per pixel", settings.samples());
+    } else if settings.adaptive_threshold() > 0.0 {
+        let adaptive = AdaptiveSampling::new(settings.min_samples(), settings.samples(), settings.adaptive_threshold());
+        renderer.set_adaptive_sampling(Some(adaptive));
+    }
+
+    let frame = if settings.progressive() {
+        let mut passes = 0;
+        let mut last_preview = SystemTime::now();
+
+        renderer.render_progressive(settings.pass_samples(), |frame, samples| {
+            passes += 1;
+
+            let write_preview = if settings.preview_seconds() > 0 {
+                match last_preview.elapsed() {
+                    Ok(t) => t.as_secs() >= settings.preview_seconds() as u64,
+                    Err(_) => true,
                 }
             } else {
-                return emitted;
+                passes % settings.preview_passes().max(1) == 0
+            };
+
+            if write_preview {
+                if settings.denoise() {
+                    let frame = denoiser.denoise(frame);
+                    export::save(&frame, &film, settings.preview_path()).expect("Unable to save preview image");
+                } else {
+                    export::save(frame, &film, settings.preview_path()).expect("Unable to save preview image");
+                }
+
+                println!("Wrote preview with {} samples per pixel", samples);
+                last_preview = SystemTime::now();
             }
-        },
-        None => {
-            return world.ambient_color_from_ray(ray);
-        }
+        })
+    } else {
+        renderer.render()
+    };
+
+    match now.elapsed() {
+        Ok(t) => println!("Took {} seconds to render", t.as_secs()),
+        Err(e) => println!("Unable to determine render time: {}", e),
     }
-}
 
-fn clampf(val: f32, min: f32, max: f32) -> f32 {
-    if val < min {
-        min
-    } else if val > max {
-        max
+    let frame = if settings.denoise() {
+        denoiser.denoise(&frame)
     } else {
-        val
+        frame
+    };
+
+    export::save(&frame, &film, settings.export_path()).expect("Unable to save output image");
+
+    if settings.aovs() {
+        export::save_aov_images(&frame, settings.export_path()).expect("Unable to save AOV images");
+    }
+
+    if let Some(path) = settings.sample_map_path() {
+        frame.sample_count_image().save(path).unwrap();
     }
-}
\ No newline at end of file
+}
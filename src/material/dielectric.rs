@@ -1,22 +1,54 @@
-extern crate rand;
-
-use material::{Material, ScatterRecord, ScatterType};
+use material::{Material, ScatterRecord, ScatterType, SurfaceBSDF, BSDF};
+use material::microfacet::{GGX, fresnel_dielectric, shading_frame};
+use texture::{Texture, ConstantTexture};
 use tdmath::{Vector3, Ray};
 use hitable::HitRecord;
-use rand::Rng;
+use sampler::Sampler;
+use onb::ONB;
 
-#[derive(Debug, Copy, Clone)]
+// Glass and liquids. Rough surfaces scatter through a GGX microfacet distribution, smooth ones
+// reflect or refract like a mirror. Light travelling inside is tinted by the transmission colour
+// at the boundary and absorbed with distance following the Beer-Lambert law.
 pub struct Dielectric {
-    ref_index: f32,
+    ref_index: Box<Texture>,
+    roughness: Box<Texture>,
+    transmission: Box<Texture>,
+    absorption: Box<Texture>,
 }
 
 impl Dielectric {
-    pub fn new(ref_index: f32) -> Self {
+    pub fn new(ref_index: Box<Texture>) -> Self {
         Dielectric {
             ref_index,
+            roughness: Box::new(ConstantTexture::new(Vector3::zero())),
+            transmission: Box::new(ConstantTexture::new(Vector3::new(1.0, 1.0, 1.0))),
+            absorption: Box::new(ConstantTexture::new(Vector3::zero())),
         }
     }
 
+    pub fn set_roughness(&mut self, roughness: Box<Texture>) {
+        self.roughness = roughness;
+    }
+
+    // Tint applied each time light is refracted through the surface
+    pub fn set_transmission(&mut self, transmission: Box<Texture>) {
+        self.transmission = transmission;
+    }
+
+    // Fraction of light absorbed per unit of distance inside, for each channel
+    pub fn set_absorption(&mut self, absorption: Box<Texture>) {
+        self.absorption = absorption;
+    }
+
+    // Textured indices of refraction at or below 1 would not bend light or divide by zero
+    fn bsdf(&self, hit_record: &HitRecord) -> DielectricBSDF {
+        let (u, v, p) = (hit_record.u(), hit_record.v(), hit_record.p());
+
+        DielectricBSDF::new(self.ref_index.scalar(u, v, p).max(1.0001),
+                            self.roughness.scalar(u, v, p),
+                            self.transmission.value(u, v, p))
+    }
+
     fn refract(v: Vector3, n: Vector3, ni_over_nt: f32) -> Option<Vector3> {
         let uv = v.normalized();
         let dt = Vector3::dot(uv, n);
@@ -30,23 +62,45 @@ impl Dielectric {
         }
     }
 
-    fn schlick(&self, cosine: f32) -> f32 {
-        let r0 = (1.0 - self.ref_index) / (1.0 + self.ref_index);
+    fn schlick(ref_index: f32, cosine: f32) -> f32 {
+        let r0 = (1.0 - ref_index) / (1.0 + ref_index);
         let r0 = r0 * r0;
 
         r0 + (1.0 - r0) * (1.0 - cosine).powf(5.0)
     }
+
+    // Light reaching a surface from inside has crossed the medium since the previous hit. Objects
+    // nested inside the medium are not accounted for.
+    fn absorbed(&self, ray: Ray, hit_record: &HitRecord) -> Vector3 {
+        if Vector3::dot(ray.direction(), hit_record.normal()) <= 0.0 {
+            return Vector3::new(1.0, 1.0, 1.0);
+        }
+
+        let absorption = self.absorption.value(hit_record.u(), hit_record.v(), hit_record.p());
+        let distance = hit_record.t() * ray.direction().length();
+        Vector3::new((-absorption.x * distance).exp(),
+                     (-absorption.y * distance).exp(),
+                     (-absorption.z * distance).exp())
+    }
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, ray: Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {        
+    fn scatter(&self, ray: Ray, hit_record: &HitRecord, sampler: &mut Sampler) -> Option<ScatterRecord> {
+        let absorbed = self.absorbed(ray, hit_record);
+        let bsdf = self.bsdf(hit_record);
+
+        if !bsdf.is_smooth() {
+            return Some(ScatterRecord::new(absorbed, ScatterType::Scatter).with_bsdf(SurfaceBSDF::Dielectric(bsdf)));
+        }
+
+        let ref_index = bsdf.ref_index;
         let (outward_normal, ni_over_nt, cosine) = if Vector3::dot(ray.direction(), hit_record.normal()) > 0.0 {
             (-hit_record.normal(),
-             self.ref_index,
-             self.ref_index * Vector3::dot(ray.direction(), hit_record.normal()) / ray.direction().length())
+             ref_index,
+             ref_index * Vector3::dot(ray.direction(), hit_record.normal()) / ray.direction().length())
         } else {
             (hit_record.normal(),
-             1.0 / self.ref_index,
+             1.0 / ref_index,
              -Vector3::dot(ray.direction(), hit_record.normal()) / ray.direction().length())
         };
 
@@ -55,25 +109,160 @@ impl Material for Dielectric {
         let reflect_prob = match Dielectric::refract(ray.direction(), outward_normal, ni_over_nt) {
             Some(refract) => {
                 refracted = refract;
-                self.schlick(cosine)
+                Dielectric::schlick(ref_index, cosine)
             },
             None => 1.0,
         };
 
-        let mut rng = rand::thread_rng();
-        let scattered = if rng.gen::<f32>() < reflect_prob {
+        let (scattered, attenuation) = if sampler.next_1d() < reflect_prob {
             let reflected = Vector3::reflect(ray.direction(), hit_record.normal());
-            Ray::new(hit_record.p(), reflected, ray.time())
+            (Ray::new(hit_record.p(), reflected, ray.time()), absorbed)
         } else {
-            Ray::new(hit_record.p(), refracted, ray.time())
+            (Ray::new(hit_record.p(), refracted, ray.time()), absorbed * bsdf.transmission)
         };
 
         let scatter_type = ScatterType::Specular(scattered);
 
-        return Some(ScatterRecord::new(Vector3::new(1.0, 1.0, 1.0), scatter_type));
+        return Some(ScatterRecord::new(attenuation, scatter_type));
+    }
+
+    fn scattering(&self, ray: Ray, hit_record: &HitRecord, scattered: Ray) -> Vector3 {
+        self.bsdf(hit_record).scattering(ray, hit_record, scattered)
+    }
+
+    fn generate(&self, ray: Ray, hit_record: &HitRecord, sampler: &mut Sampler) -> Vector3 {
+        self.bsdf(hit_record).generate(ray, hit_record, sampler)
+    }
+
+    fn pdf_value(&self, ray: Ray, hit_record: &HitRecord, direction: Vector3) -> f32 {
+        self.bsdf(hit_record).pdf_value(ray, hit_record, direction)
+    }
+
+    fn albedo(&self, hit_record: &HitRecord) -> Vector3 {
+        self.transmission.value(hit_record.u(), hit_record.v(), hit_record.p())
+    }
+}
+
+// Rough dielectric boundary with fixed parameters, the scattering of a Dielectric at one hit
+#[derive(Debug, Copy, Clone)]
+pub struct DielectricBSDF {
+    ref_index: f32,
+    distribution: GGX,
+    transmission: Vector3,
+}
+
+impl DielectricBSDF {
+    pub fn new(ref_index: f32, roughness: f32, transmission: Vector3) -> Self {
+        DielectricBSDF {
+            ref_index,
+            distribution: GGX::new(roughness, 0.0),
+            transmission,
+        }
+    }
+
+    pub fn is_smooth(&self) -> bool {
+        self.distribution.is_smooth()
     }
 
-    fn sample(&self) -> bool {
-        true
+    // Shading frame and outgoing direction along with the ratio of the index of refraction on
+    // the far side to the near side
+    fn frame_and_eta(&self, ray: Ray, hit_record: &HitRecord) -> (ONB, Vector3, f32) {
+        let (frame, wo, behind) = shading_frame(ray, hit_record, None);
+        let eta = if behind {
+            1.0 / self.ref_index
+        } else {
+            self.ref_index
+        };
+
+        (frame, wo, eta)
     }
-}
\ No newline at end of file
+
+    // Microfacet normal that scatters wo into wi, None for back facing microfacets
+    fn half_vector(wo: Vector3, wi: Vector3, eta: f32) -> Option<Vector3> {
+        let reflect = wi.z > 0.0;
+        let h = if reflect { wo + wi } else { wo + wi * eta };
+        if h.length_squared() <= 0.0 {
+            return None;
+        }
+
+        let h = h.normalized();
+        let h = if h.z < 0.0 { -h } else { h };
+
+        if Vector3::dot(h, wo) * wo.z <= 0.0 || Vector3::dot(h, wi) * wi.z <= 0.0 {
+            return None;
+        }
+
+        Some(h)
+    }
+}
+
+impl BSDF for DielectricBSDF {
+    // The radiance scaling by eta^2 on refraction cancels between entering and leaving a closed
+    // object and is left out, as for smooth surfaces
+    fn scattering(&self, ray: Ray, hit_record: &HitRecord, scattered: Ray) -> Vector3 {
+        let (frame, wo, eta) = self.frame_and_eta(ray, hit_record);
+        let wi = frame.to_local(scattered.direction().normalized());
+        if wo.z <= 0.0 || wi.z == 0.0 {
+            return Vector3::zero();
+        }
+
+        let h = match DielectricBSDF::half_vector(wo, wi, eta) {
+            Some(h) => h,
+            None => return Vector3::zero(),
+        };
+
+        let fresnel = fresnel_dielectric(Vector3::dot(wo, h), eta);
+        let d = self.distribution.d(h);
+        let g = self.distribution.g(wo, wi);
+
+        if wi.z > 0.0 {
+            let value = fresnel * d * g / (4.0 * wo.z);
+            Vector3::new(value, value, value)
+        } else {
+            let denom = Vector3::dot(wi, h) * eta + Vector3::dot(wo, h);
+            let value = (1.0 - fresnel) * d * g * eta * eta * Vector3::dot(wi, h).abs() * Vector3::dot(wo, h)
+                / (denom * denom * wo.z);
+            self.transmission * value
+        }
+    }
+
+    fn generate(&self, ray: Ray, hit_record: &HitRecord, sampler: &mut Sampler) -> Vector3 {
+        let (frame, wo, eta) = self.frame_and_eta(ray, hit_record);
+        let h = self.distribution.sample_visible_normal(wo, sampler.next_2d());
+        let cos_o = Vector3::dot(wo, h);
+        let fresnel = fresnel_dielectric(cos_o, eta);
+
+        if sampler.next_1d() < fresnel {
+            return frame.local(Vector3::reflect(-wo, h));
+        }
+
+        let sin2_t = (1.0 - cos_o * cos_o) / (eta * eta);
+        let cos_t = (1.0 - sin2_t).max(0.0).sqrt();
+        let wi = -wo / eta + h * (cos_o / eta - cos_t);
+
+        frame.local(wi)
+    }
+
+    fn pdf_value(&self, ray: Ray, hit_record: &HitRecord, direction: Vector3) -> f32 {
+        let (frame, wo, eta) = self.frame_and_eta(ray, hit_record);
+        let wi = frame.to_local(direction.normalized());
+        if wo.z <= 0.0 || wi.z == 0.0 {
+            return 0.0;
+        }
+
+        let h = match DielectricBSDF::half_vector(wo, wi, eta) {
+            Some(h) => h,
+            None => return 0.0,
+        };
+
+        let fresnel = fresnel_dielectric(Vector3::dot(wo, h), eta);
+        let pdf_h = self.distribution.visible_normal_pdf(wo, h);
+
+        if wi.z > 0.0 {
+            fresnel * pdf_h / (4.0 * Vector3::dot(wo, h))
+        } else {
+            let denom = Vector3::dot(wi, h) * eta + Vector3::dot(wo, h);
+            (1.0 - fresnel) * pdf_h * eta * eta * Vector3::dot(wi, h).abs() / (denom * denom)
+        }
+    }
+}
@@ -2,6 +2,10 @@ use material::Material;
 use texture::Texture;
 use tdmath::{Vector3, Ray};
 use hitable::HitRecord;
+use aabb::AABB;
+
+// Points per axis at which the texture is averaged to weigh the light against the others
+const EMISSION_GRID: u32 = 8;
 
 pub struct DiffuseLight {
     emit: Box<Texture>
@@ -24,7 +28,34 @@ impl Material for DiffuseLight {
         }
     }
 
-    fn sample(&self) -> bool {
+    fn is_emissive(&self) -> bool {
         true
     }
+
+    // Textures can vary with the texture coordinates or the position, so both are swept over a
+    // grid: u and v across their unit square and the position across bounds
+    fn emission(&self, bounds: &AABB) -> Vector3 {
+        let (min, extent) = (bounds.min(), bounds.max() - bounds.min());
+        let n = EMISSION_GRID;
+        let mut sum = Vector3::zero();
+
+        for i in 0..n {
+            for j in 0..n {
+                for k in 0..n {
+                    let s = (i as f32 + 0.5) / n as f32;
+                    let t = (j as f32 + 0.5) / n as f32;
+                    let r = (k as f32 + 0.5) / n as f32;
+                    let p = min + Vector3::new(extent.x * s, extent.y * t, extent.z * r);
+                    sum = sum + self.emit.value(s, t, p);
+                }
+            }
+        }
+
+        sum / (n * n * n) as f32
+    }
+
+    fn albedo(&self, hit_record: &HitRecord) -> Vector3 {
+        let c = self.emit.value(hit_record.u(), hit_record.v(), hit_record.p());
+        Vector3::new(c.r().min(1.0), c.g().min(1.0), c.b().min(1.0))
+    }
 }
\ No newline at end of file
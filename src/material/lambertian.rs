@@ -3,7 +3,7 @@ use texture::Texture;
 use tdmath::{Vector3, Ray};
 use hitable::HitRecord;
 use std::f32;
-use pdf::CosinePDF;
+use sampler::Sampler;
 
 pub struct Lambertian {
     albedo: Box<Texture>,
@@ -18,12 +18,9 @@ impl Lambertian {
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, _ray: Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+    fn scatter(&self, _ray: Ray, hit_record: &HitRecord, _sampler: &mut Sampler) -> Option<ScatterRecord> {
         let attenuation = self.albedo.value(hit_record.u(), hit_record.v(), hit_record.p());
-        let pdf = CosinePDF::new(hit_record.normal());
-        let scatter_type = ScatterType::Scatter(Box::new(pdf));
-
-        Some(ScatterRecord::new(attenuation, scatter_type))
+        Some(ScatterRecord::new(attenuation, ScatterType::Scatter))
     }
 
     fn scattering_pdf(&self, _ray: Ray, hit_record: &HitRecord, scattered: Ray) -> f32 {
@@ -34,4 +31,8 @@ impl Material for Lambertian {
             cos / f32::consts::PI
         }
     }
+
+    fn albedo(&self, hit_record: &HitRecord) -> Vector3 {
+        self.albedo.value(hit_record.u(), hit_record.v(), hit_record.p())
+    }
 }
@@ -1,34 +1,166 @@
-use material::{Material, ScatterRecord, ScatterType};
+use material::{Material, ScatterRecord, ScatterType, SurfaceBSDF, BSDF};
+use material::microfacet::{GGX, fresnel_conductor, shading_frame};
+use texture::{Texture, ConstantTexture};
 use tdmath::{Vector3, Ray};
 use hitable::HitRecord;
+use sampler::Sampler;
+use onb::ONB;
 
-#[derive(Debug, Copy, Clone)]
+// Where the colour of a metal comes from
+enum Reflectance {
+    Albedo(Box<Texture>),
+    ComplexIor { eta: Box<Texture>, k: Box<Texture> },
+}
+
+// Rough conductor with a GGX microfacet distribution. The colour comes from the complex index
+// of refraction eta + ik of each channel, so reflections turn whiter towards grazing angles.
 pub struct Metal {
-    albedo: Vector3,
-    fuzz: f32,
+    reflectance: Reflectance,
+    roughness: Box<Texture>,
+    anisotropy: Box<Texture>,
 }
 
 impl Metal {
-    pub fn new(albedo: Vector3, fuzz: f32) -> Self {
-        let fuzz = if fuzz < 1.0 {
-            fuzz
-        } else {
-            1.0
-        };
+    pub fn new(eta: Box<Texture>, k: Box<Texture>, roughness: Box<Texture>, anisotropy: Box<Texture>) -> Self {
+        Metal {
+            reflectance: Reflectance::ComplexIor { eta, k },
+            roughness,
+            anisotropy,
+        }
+    }
 
+    // Metal reflecting albedo at normal incidence and white at grazing angles
+    pub fn from_albedo(albedo: Box<Texture>, roughness: Box<Texture>, anisotropy: Box<Texture>) -> Self {
         Metal {
-            albedo,
-            fuzz,
+            reflectance: Reflectance::Albedo(albedo),
+            roughness,
+            anisotropy,
+        }
+    }
+
+    // Measured metals sampled at red, green and blue wavelengths
+    pub fn from_preset(name: &str, roughness: Box<Texture>, anisotropy: Box<Texture>) -> Option<Self> {
+        let (eta, k) = match name {
+            "gold" => (Vector3::new(0.143, 0.374, 1.442), Vector3::new(3.983, 2.386, 1.603)),
+            "copper" => (Vector3::new(0.200, 0.924, 1.102), Vector3::new(3.912, 2.452, 2.142)),
+            "aluminium" | "aluminum" => (Vector3::new(1.657, 0.880, 0.521), Vector3::new(9.224, 6.270, 4.837)),
+            _ => return None,
+        };
+
+        Some(Metal::new(Box::new(ConstantTexture::new(eta)), Box::new(ConstantTexture::new(k)), roughness, anisotropy))
+    }
+
+    // Gulbrandsen's artist friendly mapping with a white edge tint
+    fn ior_from_reflectance(reflectance: f32) -> (f32, f32) {
+        let r = reflectance.max(0.0).min(0.999);
+        let eta = (1.0 - r) / (1.0 + r);
+        let k2 = ((eta + 1.0) * (eta + 1.0) * r - (eta - 1.0) * (eta - 1.0)) / (1.0 - r);
+
+        (eta, k2.max(0.0).sqrt())
+    }
+
+    // Index of refraction and microfacet distribution at the hit
+    fn bsdf(&self, hit_record: &HitRecord) -> MetalBSDF {
+        let (u, v, p) = (hit_record.u(), hit_record.v(), hit_record.p());
+
+        let (eta, k) = match self.reflectance {
+            Reflectance::Albedo(ref albedo) => {
+                let albedo = albedo.value(u, v, p);
+                let (eta_r, k_r) = Metal::ior_from_reflectance(albedo.x);
+                let (eta_g, k_g) = Metal::ior_from_reflectance(albedo.y);
+                let (eta_b, k_b) = Metal::ior_from_reflectance(albedo.z);
+                (Vector3::new(eta_r, eta_g, eta_b), Vector3::new(k_r, k_g, k_b))
+            },
+            Reflectance::ComplexIor { ref eta, ref k } => (eta.value(u, v, p), k.value(u, v, p)),
+        };
+
+        MetalBSDF {
+            eta,
+            k,
+            distribution: GGX::new(self.roughness.scalar(u, v, p), self.anisotropy.scalar(u, v, p)),
         }
     }
 }
 
 impl Material for Metal {
-    fn scatter(&self, ray: Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
-        let reflected = Vector3::reflect(ray.direction().normalized(), hit_record.normal());
-        let scattered = Ray::new(hit_record.p(), reflected + Vector3::random_in_unit_sphere() * self.fuzz, ray.time());
-        let scatter_type = ScatterType::Specular(scattered);
+    fn scatter(&self, ray: Ray, hit_record: &HitRecord, _sampler: &mut Sampler) -> Option<ScatterRecord> {
+        let bsdf = self.bsdf(hit_record);
 
-        Some(ScatterRecord::new(self.albedo, scatter_type))
+        if bsdf.distribution.is_smooth() {
+            let (frame, wo) = MetalBSDF::brushed_frame(ray, hit_record);
+            let reflected = Vector3::reflect(ray.direction().normalized(), frame.w());
+            let scattered = Ray::new(hit_record.p(), reflected, ray.time());
+
+            return Some(ScatterRecord::new(fresnel_conductor(wo.z, bsdf.eta, bsdf.k), ScatterType::Specular(scattered)));
+        }
+
+        Some(ScatterRecord::new(Vector3::new(1.0, 1.0, 1.0), ScatterType::Scatter).with_bsdf(SurfaceBSDF::Metal(bsdf)))
     }
-}
\ No newline at end of file
+
+    fn scattering(&self, ray: Ray, hit_record: &HitRecord, scattered: Ray) -> Vector3 {
+        self.bsdf(hit_record).scattering(ray, hit_record, scattered)
+    }
+
+    fn generate(&self, ray: Ray, hit_record: &HitRecord, sampler: &mut Sampler) -> Vector3 {
+        self.bsdf(hit_record).generate(ray, hit_record, sampler)
+    }
+
+    fn pdf_value(&self, ray: Ray, hit_record: &HitRecord, direction: Vector3) -> f32 {
+        self.bsdf(hit_record).pdf_value(ray, hit_record, direction)
+    }
+
+    fn albedo(&self, hit_record: &HitRecord) -> Vector3 {
+        let bsdf = self.bsdf(hit_record);
+        fresnel_conductor(1.0, bsdf.eta, bsdf.k)
+    }
+}
+
+// Rough conductor with fixed parameters, the scattering of a Metal at one hit
+#[derive(Debug, Copy, Clone)]
+pub struct MetalBSDF {
+    eta: Vector3,
+    k: Vector3,
+    distribution: GGX,
+}
+
+impl MetalBSDF {
+    // Shading frame whose x axis follows world up projected onto the surface, so anisotropic
+    // highlights stretch the same way everywhere like metal brushed from top to bottom
+    fn brushed_frame(ray: Ray, hit_record: &HitRecord) -> (ONB, Vector3) {
+        let (frame, wo, _) = shading_frame(ray, hit_record, Some(Vector3::new(0.0, 1.0, 0.0)));
+        (frame, wo)
+    }
+}
+
+impl BSDF for MetalBSDF {
+    fn scattering(&self, ray: Ray, hit_record: &HitRecord, scattered: Ray) -> Vector3 {
+        let (frame, wo) = MetalBSDF::brushed_frame(ray, hit_record);
+        let wi = frame.to_local(scattered.direction().normalized());
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return Vector3::zero();
+        }
+
+        let h = (wo + wi).normalized();
+        let fresnel = fresnel_conductor(Vector3::dot(wo, h), self.eta, self.k);
+
+        fresnel * (self.distribution.d(h) * self.distribution.g(wo, wi) / (4.0 * wo.z))
+    }
+
+    fn generate(&self, ray: Ray, hit_record: &HitRecord, sampler: &mut Sampler) -> Vector3 {
+        let (frame, wo) = MetalBSDF::brushed_frame(ray, hit_record);
+        let h = self.distribution.sample_visible_normal(wo, sampler.next_2d());
+
+        frame.local(Vector3::reflect(-wo, h))
+    }
+
+    fn pdf_value(&self, ray: Ray, hit_record: &HitRecord, direction: Vector3) -> f32 {
+        let (frame, wo) = MetalBSDF::brushed_frame(ray, hit_record);
+        let wi = frame.to_local(direction.normalized());
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return 0.0;
+        }
+
+        let h = (wo + wi).normalized();
+        self.distribution.visible_normal_pdf(wo, h) / (4.0 * Vector3::dot(wo, h))
+    }
+}
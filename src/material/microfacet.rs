@@ -0,0 +1,168 @@
+use tdmath::{Vector3, Ray};
+use hitable::HitRecord;
+use onb::ONB;
+use std::f32;
+
+// Roughness below this is treated as a perfect mirror, where the GGX terms stop being numerically stable
+const SMOOTH_ALPHA: f32 = 1e-3;
+
+// Trowbridge-Reitz (GGX) distribution of microfacet normals. Directions are in a local frame
+// with the surface normal along +z. alpha_x and alpha_y are the roughness along x and y.
+#[derive(Debug, Copy, Clone)]
+pub struct GGX {
+    alpha_x: f32,
+    alpha_y: f32,
+}
+
+impl GGX {
+    // roughness is perceptual, from 0 (mirror) to 1. anisotropy from -1 to 1 stretches the
+    // highlight along the local x axis for positive values and along y for negative ones, so
+    // anisotropic surfaces need a frame whose x axis follows a stable tangent.
+    pub fn new(roughness: f32, anisotropy: f32) -> Self {
+        let roughness = roughness.max(0.0).min(1.0);
+        let anisotropy = anisotropy.max(-1.0).min(1.0);
+        let alpha = roughness * roughness;
+        let aspect = (1.0 - 0.9 * anisotropy.abs()).sqrt();
+
+        let (alpha_x, alpha_y) = if anisotropy >= 0.0 {
+            (alpha / aspect, alpha * aspect)
+        } else {
+            (alpha * aspect, alpha / aspect)
+        };
+
+        GGX {
+            alpha_x: alpha_x.min(1.0),
+            alpha_y: alpha_y.min(1.0),
+        }
+    }
+
+    pub fn is_smooth(&self) -> bool {
+        self.alpha_x.max(self.alpha_y) < SMOOTH_ALPHA
+    }
+
+    // Density of microfacets facing h
+    pub fn d(&self, h: Vector3) -> f32 {
+        if h.z <= 0.0 {
+            return 0.0;
+        }
+
+        let x = h.x / self.alpha_x;
+        let y = h.y / self.alpha_y;
+        let e = x * x + y * y + h.z * h.z;
+
+        1.0 / (f32::consts::PI * self.alpha_x * self.alpha_y * e * e)
+    }
+
+    fn lambda(&self, w: Vector3) -> f32 {
+        if w.z.abs() <= 0.0 {
+            return f32::MAX;
+        }
+
+        let a2 = (self.alpha_x * w.x) * (self.alpha_x * w.x) + (self.alpha_y * w.y) * (self.alpha_y * w.y);
+        0.5 * (-1.0 + (1.0 + a2 / (w.z * w.z)).sqrt())
+    }
+
+    // Fraction of microfacets visible from w
+    pub fn g1(&self, w: Vector3) -> f32 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    // Fraction of microfacets visible from both wo and wi
+    pub fn g(&self, wo: Vector3, wi: Vector3) -> f32 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    // Draws a microfacet normal visible from wo, Heitz 2018
+    pub fn sample_visible_normal(&self, wo: Vector3, u: (f32, f32)) -> Vector3 {
+        let wo = if wo.z < 0.0 { -wo } else { wo };
+        let vh = Vector3::new(self.alpha_x * wo.x, self.alpha_y * wo.y, wo.z).normalized();
+
+        let length_squared = vh.x * vh.x + vh.y * vh.y;
+        let t1 = if length_squared > 0.0 {
+            Vector3::new(-vh.y, vh.x, 0.0) / length_squared.sqrt()
+        } else {
+            Vector3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = Vector3::cross(vh, t1);
+
+        let r = u.0.sqrt();
+        let phi = 2.0 * f32::consts::PI * u.1;
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + vh.z);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).max(0.0).sqrt() + s * r * phi.sin();
+
+        let nh = t1 * p1 + t2 * p2 + vh * (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt();
+        Vector3::new(self.alpha_x * nh.x, self.alpha_y * nh.y, nh.z.max(1e-6)).normalized()
+    }
+
+    // Density of sample_visible_normal returning h
+    pub fn visible_normal_pdf(&self, wo: Vector3, h: Vector3) -> f32 {
+        if wo.z.abs() <= 0.0 {
+            return 0.0;
+        }
+
+        self.g1(wo) * Vector3::dot(wo, h).abs() * self.d(h) / wo.z.abs()
+    }
+}
+
+// Frame around the normal turned to the side the ray arrives from, the outgoing direction in it
+// and whether the ray arrives from behind the surface. The frame's x axis follows tangent if one
+// is given.
+pub fn shading_frame(ray: Ray, hit_record: &HitRecord, tangent: Option<Vector3>) -> (ONB, Vector3, bool) {
+    let wo = -ray.direction().normalized();
+    let behind = Vector3::dot(wo, hit_record.normal()) < 0.0;
+    let normal = if behind {
+        -hit_record.normal()
+    } else {
+        hit_record.normal()
+    };
+
+    let frame = match tangent {
+        Some(tangent) => ONB::from_w_and_tangent(normal, tangent),
+        None => ONB::from_w(normal),
+    };
+    let wo = frame.to_local(wo);
+    (frame, wo, behind)
+}
+
+// Unpolarized reflectance of a conductor with complex index of refraction eta + ik
+pub fn fresnel_conductor(cos_i: f32, eta: Vector3, k: Vector3) -> Vector3 {
+    Vector3::new(fresnel_conductor_channel(cos_i, eta.x, k.x),
+                 fresnel_conductor_channel(cos_i, eta.y, k.y),
+                 fresnel_conductor_channel(cos_i, eta.z, k.z))
+}
+
+fn fresnel_conductor_channel(cos_i: f32, eta: f32, k: f32) -> f32 {
+    let cos_i = cos_i.max(0.0).min(1.0);
+    let cos2 = cos_i * cos_i;
+    let sin2 = 1.0 - cos2;
+
+    let t0 = eta * eta - k * k - sin2;
+    let a2_plus_b2 = (t0 * t0 + 4.0 * eta * eta * k * k).sqrt();
+    let t1 = a2_plus_b2 + cos2;
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+    let t2 = 2.0 * cos_i * a;
+    let rs = (t1 - t2) / (t1 + t2);
+
+    let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+
+    0.5 * (rs + rp)
+}
+
+// Unpolarized reflectance of a dielectric boundary. cos_i is measured on the side the light
+// arrives from and eta is the ratio of the index on the far side to the near side.
+pub fn fresnel_dielectric(cos_i: f32, eta: f32) -> f32 {
+    let cos_i = cos_i.max(0.0).min(1.0);
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let rs = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    let rp = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+
+    0.5 * (rs * rs + rp * rp)
+}
@@ -4,19 +4,25 @@ pub mod dielectric;
 pub mod metal;
 pub mod diffuselight;
 pub mod nomaterial;
+pub mod microfacet;
+pub mod principled;
 
-pub use self::scatterrecord::{ScatterRecord, ScatterType};
+pub use self::scatterrecord::{ScatterRecord, ScatterType, SurfaceBSDF};
 pub use self::lambertian::Lambertian;
-pub use self::dielectric::Dielectric;
-pub use self::metal::Metal;
+pub use self::dielectric::{Dielectric, DielectricBSDF};
+pub use self::metal::{Metal, MetalBSDF};
 pub use self::diffuselight::DiffuseLight;
 pub use self::nomaterial::NoMaterial;
+pub use self::principled::{Principled, PrincipledBSDF};
 
 use tdmath::{Vector3, Ray};
 use hitable::HitRecord;
+use aabb::AABB;
+use sampler::Sampler;
+use pdf::CosinePDF;
 
 pub trait Material: Send + Sync {
-    fn scatter(&self, _ray: Ray, _hit_record: &HitRecord) -> Option<ScatterRecord> {
+    fn scatter(&self, _ray: Ray, _hit_record: &HitRecord, _sampler: &mut Sampler) -> Option<ScatterRecord> {
         None
     }
 
@@ -24,11 +30,50 @@ pub trait Material: Send + Sync {
         0.0
     }
 
+    // Light scattered towards scattered relative to the attenuation, including the cosine term.
+    // Materials whose tint depends on the scattered direction override this instead of
+    // scattering_pdf.
+    fn scattering(&self, ray: Ray, hit_record: &HitRecord, scattered: Ray) -> Vector3 {
+        let pdf = self.scattering_pdf(ray, hit_record, scattered);
+        Vector3::new(pdf, pdf, pdf)
+    }
+
+    // Direction of a ScatterType::Scatter, cosine weighted around the normal unless overridden
+    fn generate(&self, _ray: Ray, hit_record: &HitRecord, sampler: &mut Sampler) -> Vector3 {
+        CosinePDF::new(hit_record.normal()).generate(sampler)
+    }
+
+    // Density of the directions returned by generate
+    fn pdf_value(&self, _ray: Ray, hit_record: &HitRecord, direction: Vector3) -> f32 {
+        CosinePDF::new(hit_record.normal()).value(direction)
+    }
+
     fn emit(&self, _ray: Ray, _hit: &HitRecord, _u: f32, _v: f32, _p: Vector3) -> Vector3 {
         Vector3::zero()
     }
 
-    fn sample(&self) -> bool {
+    // Objects with emissive materials become lights
+    fn is_emissive(&self) -> bool {
         false
     }
-}
\ No newline at end of file
+
+    // Average radiance emitted by the material over a surface lying within bounds
+    fn emission(&self, _bounds: &AABB) -> Vector3 {
+        Vector3::zero()
+    }
+
+    // Surface colour used for the albedo AOV
+    fn albedo(&self, _hit_record: &HitRecord) -> Vector3 {
+        Vector3::zero()
+    }
+}
+
+// Scattering of a material at one hit with its parameters already looked up. Directions are
+// the same as for the Material methods of the same name.
+pub trait BSDF {
+    fn scattering(&self, ray: Ray, hit_record: &HitRecord, scattered: Ray) -> Vector3;
+
+    fn generate(&self, ray: Ray, hit_record: &HitRecord, sampler: &mut Sampler) -> Vector3;
+
+    fn pdf_value(&self, ray: Ray, hit_record: &HitRecord, direction: Vector3) -> f32;
+}
@@ -0,0 +1,277 @@
+use material::{Material, ScatterRecord, ScatterType, SurfaceBSDF, DielectricBSDF, BSDF};
+use material::microfacet::{GGX, shading_frame};
+use texture::{Texture, ConstantTexture};
+use tdmath::{Vector3, Ray};
+use hitable::HitRecord;
+use sampler::{Sampler, sample_cosine_direction};
+use std::f32;
+
+// Roughness is kept above this so every lobe has a finite density and can be light sampled
+const MIN_ROUGHNESS: f32 = 0.05;
+
+// The clearcoat is a thin glossy varnish with a fixed roughness and index of refraction 1.5
+const CLEARCOAT_ROUGHNESS: f32 = 0.3;
+const CLEARCOAT_F0: f32 = 0.04;
+
+// Disney style uber-material blending a diffuse base, a dielectric or metallic specular layer,
+// rough glass, sheen and a clearcoat. Every parameter is read from a texture at the hit point.
+pub struct Principled {
+    base_color: Box<Texture>,
+    metallic: Box<Texture>,
+    roughness: Box<Texture>,
+    specular: Box<Texture>,
+    clearcoat: Box<Texture>,
+    sheen: Box<Texture>,
+    transmission: Box<Texture>,
+}
+
+// Parameters looked up at a single hit
+#[derive(Debug, Copy, Clone)]
+struct Parameters {
+    base_color: Vector3,
+    metallic: f32,
+    roughness: f32,
+    specular: f32,
+    clearcoat: f32,
+    sheen: f32,
+    transmission: f32,
+}
+
+// Chance of sampling each lobe
+#[derive(Debug, Copy, Clone)]
+struct LobeWeights {
+    diffuse: f32,
+    specular: f32,
+    clearcoat: f32,
+    transmission: f32,
+}
+
+impl Principled {
+    pub fn new(base_color: Box<Texture>) -> Self {
+        Principled {
+            base_color,
+            metallic: constant(0.0),
+            roughness: constant(0.5),
+            specular: constant(0.5),
+            clearcoat: constant(0.0),
+            sheen: constant(0.0),
+            transmission: constant(0.0),
+        }
+    }
+
+    pub fn set_metallic(&mut self, metallic: Box<Texture>) {
+        self.metallic = metallic;
+    }
+
+    pub fn set_roughness(&mut self, roughness: Box<Texture>) {
+        self.roughness = roughness;
+    }
+
+    // Strength of the reflection on non-metals, 0.5 matches an index of refraction of 1.5
+    pub fn set_specular(&mut self, specular: Box<Texture>) {
+        self.specular = specular;
+    }
+
+    pub fn set_clearcoat(&mut self, clearcoat: Box<Texture>) {
+        self.clearcoat = clearcoat;
+    }
+
+    // Soft white rim seen at grazing angles on cloth
+    pub fn set_sheen(&mut self, sheen: Box<Texture>) {
+        self.sheen = sheen;
+    }
+
+    // Fraction of the non-metallic base that is glass tinted by the base colour
+    pub fn set_transmission(&mut self, transmission: Box<Texture>) {
+        self.transmission = transmission;
+    }
+
+    fn parameters(&self, hit_record: &HitRecord) -> Parameters {
+        let (u, v, p) = (hit_record.u(), hit_record.v(), hit_record.p());
+
+        Parameters {
+            base_color: self.base_color.value(u, v, p),
+            metallic: saturate(self.metallic.scalar(u, v, p)),
+            roughness: saturate(self.roughness.scalar(u, v, p)).max(MIN_ROUGHNESS),
+            specular: saturate(self.specular.scalar(u, v, p)),
+            clearcoat: saturate(self.clearcoat.scalar(u, v, p)),
+            sheen: saturate(self.sheen.scalar(u, v, p)),
+            transmission: saturate(self.transmission.scalar(u, v, p)),
+        }
+    }
+
+    fn bsdf(&self, hit_record: &HitRecord) -> PrincipledBSDF {
+        let parameters = self.parameters(hit_record);
+
+        PrincipledBSDF {
+            parameters,
+            weights: Principled::lobe_weights(&parameters),
+            glass: Principled::glass(&parameters),
+        }
+    }
+
+    fn lobe_weights(parameters: &Parameters) -> LobeWeights {
+        let dielectric = 1.0 - parameters.metallic;
+        let diffuse = dielectric * (1.0 - parameters.transmission + parameters.transmission * parameters.sheen);
+        let specular = parameters.metallic + dielectric * (1.0 - parameters.transmission);
+        let clearcoat = 0.25 * parameters.clearcoat;
+        let transmission = dielectric * parameters.transmission;
+        let total = diffuse + specular + clearcoat + transmission;
+
+        LobeWeights {
+            diffuse: diffuse / total,
+            specular: specular / total,
+            clearcoat: clearcoat / total,
+            transmission: transmission / total,
+        }
+    }
+
+    // Rough glass making up the transmissive part of the base
+    fn glass(parameters: &Parameters) -> DielectricBSDF {
+        let f0 = (0.08 * parameters.specular).min(0.99);
+        let ref_index = (1.0 + f0.sqrt()) / (1.0 - f0.sqrt());
+
+        DielectricBSDF::new(ref_index.max(1.0001), parameters.roughness, parameters.base_color)
+    }
+}
+
+impl Material for Principled {
+    fn scatter(&self, _ray: Ray, hit_record: &HitRecord, _sampler: &mut Sampler) -> Option<ScatterRecord> {
+        let bsdf = SurfaceBSDF::Principled(self.bsdf(hit_record));
+        Some(ScatterRecord::new(Vector3::new(1.0, 1.0, 1.0), ScatterType::Scatter).with_bsdf(bsdf))
+    }
+
+    fn scattering(&self, ray: Ray, hit_record: &HitRecord, scattered: Ray) -> Vector3 {
+        self.bsdf(hit_record).scattering(ray, hit_record, scattered)
+    }
+
+    fn generate(&self, ray: Ray, hit_record: &HitRecord, sampler: &mut Sampler) -> Vector3 {
+        self.bsdf(hit_record).generate(ray, hit_record, sampler)
+    }
+
+    fn pdf_value(&self, ray: Ray, hit_record: &HitRecord, direction: Vector3) -> f32 {
+        self.bsdf(hit_record).pdf_value(ray, hit_record, direction)
+    }
+
+    fn albedo(&self, hit_record: &HitRecord) -> Vector3 {
+        self.base_color.value(hit_record.u(), hit_record.v(), hit_record.p())
+    }
+}
+
+// Layers of a Principled material with its parameters looked up at one hit
+#[derive(Debug, Copy, Clone)]
+pub struct PrincipledBSDF {
+    parameters: Parameters,
+    weights: LobeWeights,
+    glass: DielectricBSDF,
+}
+
+impl BSDF for PrincipledBSDF {
+    fn scattering(&self, ray: Ray, hit_record: &HitRecord, scattered: Ray) -> Vector3 {
+        let parameters = &self.parameters;
+        let (frame, wo, _) = shading_frame(ray, hit_record, None);
+        let wi = frame.to_local(scattered.direction().normalized());
+        if wo.z <= 0.0 {
+            return Vector3::zero();
+        }
+
+        let dielectric = 1.0 - parameters.metallic;
+        let mut value = Vector3::zero();
+
+        if parameters.transmission > 0.0 && dielectric > 0.0 {
+            value = value + self.glass.scattering(ray, hit_record, scattered) * (dielectric * parameters.transmission);
+        }
+
+        if wi.z <= 0.0 {
+            return value;
+        }
+
+        let h = (wo + wi).normalized();
+        let cos_d = Vector3::dot(wi, h);
+        let base = dielectric * (1.0 - parameters.transmission);
+        let white = Vector3::new(1.0, 1.0, 1.0);
+        let dielectric_f0 = 0.08 * parameters.specular;
+
+        // Light reflected by the specular layer never reaches the diffuse base below it
+        let entering = 1.0 - schlick(white * dielectric_f0, wo.z).x;
+        let diffuse = parameters.base_color * (base * entering * wi.z / f32::consts::PI);
+        let sheen = white * (dielectric * parameters.sheen * schlick_weight(cos_d) * wi.z);
+
+        let distribution = GGX::new(parameters.roughness, 0.0);
+        let fresnel = schlick(parameters.base_color, Vector3::dot(wo, h)) * parameters.metallic
+            + schlick(white * dielectric_f0, Vector3::dot(wo, h)) * base;
+        let specular = fresnel * (distribution.d(h) * distribution.g(wo, wi) / (4.0 * wo.z));
+
+        let coat = GGX::new(CLEARCOAT_ROUGHNESS, 0.0);
+        let coat_fresnel = schlick(white * CLEARCOAT_F0, Vector3::dot(wo, h)).x;
+        let clearcoat = white * (0.25 * parameters.clearcoat * coat_fresnel * coat.d(h) * coat.g(wo, wi) / (4.0 * wo.z));
+
+        // The clearcoat dims everything below it in the same way
+        let under_coat = 1.0 - 0.25 * parameters.clearcoat * schlick(white * CLEARCOAT_F0, wo.z).x;
+
+        (value + diffuse + sheen + specular) * under_coat + clearcoat
+    }
+
+    fn generate(&self, ray: Ray, hit_record: &HitRecord, sampler: &mut Sampler) -> Vector3 {
+        let parameters = &self.parameters;
+        let weights = &self.weights;
+        let (frame, wo, _) = shading_frame(ray, hit_record, None);
+
+        let lobe = sampler.next_1d();
+        if lobe < weights.diffuse {
+            frame.local(sample_cosine_direction(sampler.next_2d()))
+        } else if lobe < weights.diffuse + weights.specular {
+            let h = GGX::new(parameters.roughness, 0.0).sample_visible_normal(wo, sampler.next_2d());
+            frame.local(Vector3::reflect(-wo, h))
+        } else if lobe < weights.diffuse + weights.specular + weights.clearcoat {
+            let h = GGX::new(CLEARCOAT_ROUGHNESS, 0.0).sample_visible_normal(wo, sampler.next_2d());
+            frame.local(Vector3::reflect(-wo, h))
+        } else {
+            self.glass.generate(ray, hit_record, sampler)
+        }
+    }
+
+    fn pdf_value(&self, ray: Ray, hit_record: &HitRecord, direction: Vector3) -> f32 {
+        let parameters = &self.parameters;
+        let weights = &self.weights;
+        let (frame, wo, _) = shading_frame(ray, hit_record, None);
+        let wi = frame.to_local(direction.normalized());
+        if wo.z <= 0.0 {
+            return 0.0;
+        }
+
+        let mut pdf = 0.0;
+        if weights.transmission > 0.0 {
+            pdf += weights.transmission * self.glass.pdf_value(ray, hit_record, direction);
+        }
+
+        if wi.z <= 0.0 {
+            return pdf;
+        }
+
+        let h = (wo + wi).normalized();
+        let cos_o = Vector3::dot(wo, h);
+
+        pdf += weights.diffuse * wi.z / f32::consts::PI;
+        pdf += weights.specular * GGX::new(parameters.roughness, 0.0).visible_normal_pdf(wo, h) / (4.0 * cos_o);
+        pdf += weights.clearcoat * GGX::new(CLEARCOAT_ROUGHNESS, 0.0).visible_normal_pdf(wo, h) / (4.0 * cos_o);
+        pdf
+    }
+}
+
+fn constant(value: f32) -> Box<Texture> {
+    Box::new(ConstantTexture::new(Vector3::new(value, value, value)))
+}
+
+fn saturate(value: f32) -> f32 {
+    value.max(0.0).min(1.0)
+}
+
+fn schlick_weight(cosine: f32) -> f32 {
+    (1.0 - saturate(cosine)).powi(5)
+}
+
+fn schlick(f0: Vector3, cosine: f32) -> Vector3 {
+    let white = Vector3::new(1.0, 1.0, 1.0);
+    f0 + (white - f0) * schlick_weight(cosine)
+}
@@ -1,14 +1,55 @@
 use tdmath::{Vector3, Ray};
-use pdf::PDF;
+use material::{BSDF, MetalBSDF, DielectricBSDF, PrincipledBSDF};
+use hitable::HitRecord;
+use sampler::Sampler;
 
+// Specular scatters follow the given ray. Other scatters draw directions from generate and
+// weight them with pdf_value, of the record's BSDF when it has one and otherwise the material's.
+#[derive(Copy, Clone)]
 pub enum ScatterType {
     Specular(Ray),
-    Scatter(Box<PDF>)
+    Scatter,
+}
+
+// Scattering handed over by a material at one hit. Kept inline so that nothing has to be
+// allocated per bounce.
+#[derive(Debug, Copy, Clone)]
+pub enum SurfaceBSDF {
+    Metal(MetalBSDF),
+    Dielectric(DielectricBSDF),
+    Principled(PrincipledBSDF),
+}
+
+impl BSDF for SurfaceBSDF {
+    fn scattering(&self, ray: Ray, hit_record: &HitRecord, scattered: Ray) -> Vector3 {
+        match *self {
+            SurfaceBSDF::Metal(ref bsdf) => bsdf.scattering(ray, hit_record, scattered),
+            SurfaceBSDF::Dielectric(ref bsdf) => bsdf.scattering(ray, hit_record, scattered),
+            SurfaceBSDF::Principled(ref bsdf) => bsdf.scattering(ray, hit_record, scattered),
+        }
+    }
+
+    fn generate(&self, ray: Ray, hit_record: &HitRecord, sampler: &mut Sampler) -> Vector3 {
+        match *self {
+            SurfaceBSDF::Metal(ref bsdf) => bsdf.generate(ray, hit_record, sampler),
+            SurfaceBSDF::Dielectric(ref bsdf) => bsdf.generate(ray, hit_record, sampler),
+            SurfaceBSDF::Principled(ref bsdf) => bsdf.generate(ray, hit_record, sampler),
+        }
+    }
+
+    fn pdf_value(&self, ray: Ray, hit_record: &HitRecord, direction: Vector3) -> f32 {
+        match *self {
+            SurfaceBSDF::Metal(ref bsdf) => bsdf.pdf_value(ray, hit_record, direction),
+            SurfaceBSDF::Dielectric(ref bsdf) => bsdf.pdf_value(ray, hit_record, direction),
+            SurfaceBSDF::Principled(ref bsdf) => bsdf.pdf_value(ray, hit_record, direction),
+        }
+    }
 }
 
 pub struct ScatterRecord {
     attenuation: Vector3,
-    scatter_type: ScatterType
+    scatter_type: ScatterType,
+    bsdf: Option<SurfaceBSDF>,
 }
 
 impl ScatterRecord {
@@ -16,14 +57,26 @@ impl ScatterRecord {
         ScatterRecord {
             attenuation,
             scatter_type,
+            bsdf: None,
         }
     }
 
+    // Materials whose parameters are costly to look up hand over the scattering at this hit,
+    // which is then used in place of the material for the rest of the bounce
+    pub fn with_bsdf(mut self, bsdf: SurfaceBSDF) -> Self {
+        self.bsdf = Some(bsdf);
+        self
+    }
+
     pub fn attenuation(&self) -> Vector3 {
         self.attenuation
     }
 
-    pub fn scatter_type(self) -> ScatterType {
+    pub fn scatter_type(&self) -> ScatterType {
         self.scatter_type
     }
+
+    pub fn bsdf(&self) -> Option<&SurfaceBSDF> {
+        self.bsdf.as_ref()
+    }
 }
@@ -1,9 +1,8 @@
-extern crate rand;
 extern crate tdmath;
 
-use self::rand::Rng;
 use tdmath::Vector3;
 use std::num::Wrapping;
+use sampler::{hash, to_unit_float};
 
 pub struct Perlin {
     ranvec: Vec<Vector3>,
@@ -13,12 +12,13 @@ pub struct Perlin {
 }
 
 impl Perlin {
-    pub fn new() -> Perlin {
+    // The same seed always produces the same noise
+    pub fn new(seed: u32) -> Perlin {
         Perlin {
-            ranvec: Perlin::perlin_generate(),
-            perm_x: Perlin::perlin_generate_perm(),
-            perm_y: Perlin::perlin_generate_perm(),
-            perm_z: Perlin::perlin_generate_perm(),
+            ranvec: Perlin::perlin_generate(hash(seed, 0)),
+            perm_x: Perlin::perlin_generate_perm(hash(seed, 1)),
+            perm_y: Perlin::perlin_generate_perm(hash(seed, 2)),
+            perm_z: Perlin::perlin_generate_perm(hash(seed, 3)),
         }
     }
 
@@ -48,14 +48,13 @@ impl Perlin {
         return Perlin::perlin_interp(c, u, v, w);
     }
 
-    fn perlin_generate() -> Vec<Vector3> {
+    fn perlin_generate(seed: u32) -> Vec<Vector3> {
         let mut p = Vec::with_capacity(256);
-        let mut rng = rand::thread_rng();
 
-        for _ in 0..256 {
-            let x = -1.0 + 2.0 * rng.gen::<f32>();
-            let y = -1.0 + 2.0 * rng.gen::<f32>();
-            let z = -1.0 + 2.0 * rng.gen::<f32>();
+        for i in 0..256 {
+            let x = -1.0 + 2.0 * to_unit_float(hash(seed, 3 * i));
+            let y = -1.0 + 2.0 * to_unit_float(hash(seed, 3 * i + 1));
+            let z = -1.0 + 2.0 * to_unit_float(hash(seed, 3 * i + 2));
 
             p.push(Vector3::new(x, y, z).normalized());
         }
@@ -63,23 +62,21 @@ impl Perlin {
         return p;
     }
 
-    fn permute(p: &mut Vec<usize>, n: usize) {
-        let mut rng = rand::thread_rng();
-
+    fn permute(p: &mut Vec<usize>, n: usize, seed: u32) {
         for i in (1..n).rev() {
-            let target = (rng.gen::<f32>() * (i as f32 + 1.0)) as usize;
+            let target = (to_unit_float(hash(seed, i as u32)) * (i as f32 + 1.0)) as usize;
             let tmp = p[i];
             p[i] = p[target];
             p[target] = tmp;
         }
     }
 
-    fn perlin_generate_perm() -> Vec<usize> {
+    fn perlin_generate_perm(seed: u32) -> Vec<usize> {
         let mut p = Vec::with_capacity(256);
         for i in 0..256 {
             p.push(i);
         }
-        Perlin::permute(&mut p, 256);
+        Perlin::permute(&mut p, 256, seed);
         return p;
     }
 
@@ -22,6 +22,23 @@ impl ONB {
         }
     }
 
+    // Basis around n with u along the tangent closest to the given direction. The orientation
+    // changes smoothly over a surface, except where n is parallel to tangent.
+    pub fn from_w_and_tangent(n: Vector3, tangent: Vector3) -> ONB {
+        let w = n.normalized();
+        let t = tangent - w * Vector3::dot(w, tangent);
+        if t.length_squared() < 1e-8 {
+            return ONB::from_w(w);
+        }
+
+        let u = t.normalized();
+        let v = Vector3::cross(u, w);
+
+        ONB {
+            axis: [u, v, w],
+        }
+    }
+
     pub fn u(&self) -> Vector3 {
         self.axis[0]
     }
@@ -37,4 +54,9 @@ impl ONB {
     pub fn local(&self, v: Vector3) -> Vector3 {
         v.x * self.u() + v.y * self.v() + v.z * self.w()
     }
+
+    // Inverse of local, expresses a world space vector in the basis
+    pub fn to_local(&self, v: Vector3) -> Vector3 {
+        Vector3::new(Vector3::dot(v, self.u()), Vector3::dot(v, self.v()), Vector3::dot(v, self.w()))
+    }
 }
\ No newline at end of file
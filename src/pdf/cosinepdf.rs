@@ -1,22 +1,21 @@
 use onb::ONB;
 use tdmath::Vector3;
-use pdf::PDF;
 use std::f32;
+use sampler::{Sampler, sample_cosine_direction};
 
 pub struct CosinePDF {
     uvw: ONB,
 }
 
+// Directions around w with density proportional to their cosine with w
 impl CosinePDF {
     pub fn new(w: Vector3) -> CosinePDF {
         CosinePDF {
             uvw: ONB::from_w(w),
         }
     }
-}
 
-impl PDF for CosinePDF {
-    fn value(&self, direction: Vector3) -> f32 {
+    pub fn value(&self, direction: Vector3) -> f32 {
         let cosine = Vector3::dot(direction.normalized(), self.uvw.w());
         if cosine > 0.0 {
             cosine / f32::consts::PI
@@ -25,7 +24,7 @@ impl PDF for CosinePDF {
         }
     }
 
-    fn generate(&self) -> Vector3 {
-        self.uvw.local(Vector3::random_cosine_direction())
+    pub fn generate(&self, sampler: &mut Sampler) -> Vector3 {
+        self.uvw.local(sample_cosine_direction(sampler.next_2d()))
     }
 }
\ No newline at end of file
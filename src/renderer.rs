@@ -0,0 +1,371 @@
+extern crate rand;
+extern crate tdmath;
+extern crate toml;
+extern crate threadpool;
+
+use tdmath::Vector3;
+use world::World;
+use camera::Camera;
+use toml::Value;
+use rendertile::RenderTile;
+use framebuffer::FrameBuffer;
+use threadpool::ThreadPool;
+use std::sync::mpsc::channel;
+use std::sync::mpsc::{Sender, Receiver};
+use std::sync::Arc;
+use adaptive::{AdaptiveSampling, PixelStats};
+use aov::AovAccumulator;
+use filter::Filter;
+use roulette::RussianRoulette;
+use integrator::{Integrator, PathTracer};
+use light::LightList;
+use sampler::{Sampler, IndependentSampler};
+
+pub struct Renderer {
+    world: Arc<World>,
+    lights: Arc<LightList>,
+    camera: Camera,
+    width: u32,
+    height: u32,
+    samples: u32,
+    threads: u32,
+    tile_size: u32,
+    adaptive: Option<AdaptiveSampling>,
+    aovs: bool,
+    filter: Filter,
+    sampler: Box<Sampler>,
+    // Path length of the default path tracer, used unless another integrator is set
+    roulette: RussianRoulette,
+    integrator: Option<Arc<Integrator>>,
+}
+
+impl Renderer {
+    pub fn new(world: World, lights: LightList, camera: Camera, width: u32, height: u32) -> Self {
+        Renderer {
+            world: Arc::new(world),
+            lights: Arc::new(lights),
+            camera,
+            width,
+            height,
+            samples: 100,
+            threads: 4,
+            tile_size: 32,
+            adaptive: None,
+            aovs: false,
+            filter: Filter::default(),
+            sampler: Box::new(IndependentSampler::new(rand::random())),
+            roulette: RussianRoulette::default(),
+            integrator: None,
+        }
+    }
+
+    // The same seed, scene and settings always render the same image regardless of the
+    // number of threads. seed is used for procedural textures and the default sampler.
+    pub fn from_toml(scene: &Value, width: u32, height: u32, seed: u32) -> Self {
+        let world = World::from_toml(scene, seed);
+        let lights = world.lights_from_toml(scene);
+        let camera = Camera::from_toml(&scene["camera"], width as f32 / height as f32);
+
+        let mut renderer = Renderer::new(world, lights, camera, width, height);
+        renderer.set_sampler(Box::new(IndependentSampler::new(seed)));
+        if let Some(film_data) = scene.get("film") {
+            renderer.set_filter(Filter::from_toml(film_data));
+        }
+
+        if let Some(render_data) = scene.get("render") {
+            renderer.set_roulette(RussianRoulette::from_toml(render_data));
+        }
+
+        renderer
+    }
+
+    pub fn set_samples(&mut self, samples: u32) {
+        self.samples = samples;
+    }
+
+    pub fn set_threads(&mut self, threads: u32) {
+        self.threads = threads;
+    }
+
+    pub fn set_tile_size(&mut self, tile_size: u32) {
+        self.tile_size = if tile_size > 0 {
+            tile_size
+        } else {
+            1
+        };
+    }
+
+    // Only applies to render, progressive passes always take a fixed number of samples and
+    // ignore it
+    pub fn set_adaptive_sampling(&mut self, adaptive: Option<AdaptiveSampling>) {
+        self.adaptive = adaptive;
+    }
+
+    // Gathers depth, normal, albedo and id passes from the first hit of each camera ray
+    pub fn set_aovs(&mut self, aovs: bool) {
+        self.aovs = aovs;
+    }
+
+    pub fn filter(&self) -> Filter {
+        self.filter
+    }
+
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.filter = filter;
+    }
+
+    // Each tile renders with its own copy of sampler
+    pub fn set_sampler(&mut self, sampler: Box<Sampler>) {
+        self.sampler = sampler;
+    }
+
+    pub fn roulette(&self) -> RussianRoulette {
+        self.roulette
+    }
+
+    // Has no effect on an integrator given to set_integrator
+    pub fn set_roulette(&mut self, roulette: RussianRoulette) {
+        self.roulette = roulette;
+    }
+
+    // Replaces the default path tracer
+    pub fn set_integrator(&mut self, integrator: Box<Integrator>) {
+        self.integrator = Some(Arc::from(integrator));
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn render(&self) -> FrameBuffer {
+        let pool = ThreadPool::new(self.threads as usize);
+        self.render_pass(&pool, self.samples, 0, self.adaptive)
+    }
+
+    // Renders the image in passes of pass_samples samples per pixel. After every pass
+    // on_pass is called with the image accumulated so far and the samples taken per pixel.
+    pub fn render_progressive<F>(&self, pass_samples: u32, mut on_pass: F) -> FrameBuffer
+        where F: FnMut(&FrameBuffer, u32) {
+        let pool = ThreadPool::new(self.threads as usize);
+        let pass_samples = if pass_samples > 0 {
+            pass_samples
+        } else {
+            1
+        };
+
+        let mut sum = FrameBuffer::new(self.width, self.height);
+        let mut frame = FrameBuffer::new(self.width, self.height);
+        let mut samples_done = 0;
+
+        while samples_done < self.samples {
+            let ns = if samples_done + pass_samples > self.samples {
+                self.samples - samples_done
+            } else {
+                pass_samples
+            };
+
+            let pass = self.render_pass(&pool, ns, samples_done, None);
+            sum.add_weighted(&pass, ns as f32);
+            samples_done += ns;
+
+            frame = sum.scaled(1.0 / samples_done as f32);
+            on_pass(&frame, samples_done);
+        }
+
+        frame
+    }
+
+    // first_sample is the index of the first sample taken per pixel, so that passes continue
+    // the sampler's sequence instead of repeating it
+    fn render_pass(&self, pool: &ThreadPool, samples: u32, first_sample: u32, adaptive: Option<AdaptiveSampling>) -> FrameBuffer {
+        let nx = self.width;
+        let ny = self.height;
+        let ns = samples;
+
+        let margin = (self.filter.radius() - 0.5).ceil().max(0.0) as u32;
+        let tiles = Renderer::create_tiles(nx, ny, self.tile_size, margin);
+
+        let settings = PassSettings {
+            image_width: nx,
+            image_height: ny,
+            samples: ns,
+            first_sample,
+            adaptive,
+            filter: self.filter,
+        };
+
+        let integrator: Arc<Integrator> = match self.integrator {
+            Some(ref integrator) => Arc::clone(integrator),
+            None => Arc::new(PathTracer::new(self.roulette)),
+        };
+
+        let (tx, rx): (Sender<RenderTile>, Receiver<RenderTile>) = channel();
+
+        // Render tiles
+        for mut tile in tiles {
+            if self.aovs {
+                tile.enable_aovs();
+            }
+
+            let tx = tx.clone();
+            let world = Arc::clone(&self.world);
+            let lights = Arc::clone(&self.lights);
+            let camera = self.camera;
+            let mut sampler = self.sampler.clone_box();
+            let integrator = Arc::clone(&integrator);
+
+            pool.execute(move || {
+                render_tile(&mut tile, &camera, &*world, &*lights, &*integrator, &mut *sampler, &settings);
+                tx.send(tile).expect("Unable to send data");
+            });
+        }
+        drop(tx);
+
+        // Draw tiles to result
+        let mut frame = FrameBuffer::new(nx, ny);
+        let mut pixels = vec![Vector3::zero(); (nx * ny) as usize];
+        let mut weights = vec![0.0; (nx * ny) as usize];
+        let mut abs_weights = vec![0.0; (nx * ny) as usize];
+        // Tiles finish in any order, sort them so filtered samples are always summed in the same order
+        let mut tiles: Vec<RenderTile> = rx.iter().collect();
+        tiles.sort_by_key(|tile| (tile.y(), tile.x()));
+        for tile in &tiles {
+            frame.copy_from_tile(tile);
+            tile.splat_into(&mut pixels, &mut weights, &mut abs_weights, nx, ny);
+        }
+
+        for y in 0..ny {
+            for x in 0..nx {
+                let index = (y * nx + x) as usize;
+                let col = resolve_pixel(pixels[index], weights[index], abs_weights[index]);
+
+                frame.put_pixel(x, ny - 1 - y, col);
+            }
+        }
+
+        frame
+    }
+
+    // Covers the image with tiles of tile_size x tile_size, shrinking the last row and column to fit
+    fn create_tiles(width: u32, height: u32, tile_size: u32, margin: u32) -> Vec<RenderTile> {
+        let mut tiles = Vec::new();
+
+        let mut y = 0;
+        while y < height {
+            let tile_height = if y + tile_size > height {
+                height - y
+            } else {
+                tile_size
+            };
+
+            let mut x = 0;
+            while x < width {
+                let tile_width = if x + tile_size > width {
+                    width - x
+                } else {
+                    tile_size
+                };
+
+                tiles.push(RenderTile::with_margin(x, y, tile_width, tile_height, margin));
+                x += tile_size;
+            }
+
+            y += tile_size;
+        }
+
+        tiles
+    }
+}
+
+// Filters with negative lobes can leave a pixel with a summed weight near or below zero at low
+// sample counts. Dividing by it would blow the pixel up or flip its sign, so such pixels are
+// normalised by the summed magnitude of the weights instead.
+fn resolve_pixel(color: Vector3, weight: f32, abs_weight: f32) -> Vector3 {
+    let col = if weight > 0.1 * abs_weight {
+        color / weight
+    } else if abs_weight > 0.0 {
+        color / abs_weight
+    } else {
+        Vector3::zero()
+    };
+
+    Vector3::new(col.x.max(0.0), col.y.max(0.0), col.z.max(0.0))
+}
+
+// Settings shared by every tile of a render pass
+#[derive(Debug, Copy, Clone)]
+pub struct PassSettings {
+    pub image_width: u32,
+    pub image_height: u32,
+    // Samples per pixel, unless adaptive is set
+    pub samples: u32,
+    // Index of the first sample taken per pixel
+    pub first_sample: u32,
+    pub adaptive: Option<AdaptiveSampling>,
+    pub filter: Filter,
+}
+
+// Renders samples rays per pixel, or when adaptive is set, between its min and max
+// samples depending on how quickly each pixel converges. Samples are numbered from
+// first_sample and splatted into the tile using filter.
+pub fn render_tile(tile: &mut RenderTile, camera: &Camera, world: &World, lights: &LightList, integrator: &Integrator, sampler: &mut Sampler, settings: &PassSettings) {
+    let x = tile.x();
+    let y = tile.y();
+    let x_end = x + tile.width();
+    let y_end = y + tile.height();
+    let (image_width, image_height) = (settings.image_width, settings.image_height);
+    let (first_sample, adaptive, filter) = (settings.first_sample, settings.adaptive, &settings.filter);
+    let max_samples = match adaptive {
+        Some(adaptive) => adaptive.max_samples(),
+        None => settings.samples,
+    };
+
+    for j in y..y_end {
+        for i in x..x_end {
+            sampler.start_pixel(i, j, max_samples);
+
+            let mut stats = PixelStats::new();
+            let mut aov = AovAccumulator::new();
+            let mut taken = 0;
+            while taken < max_samples {
+                sampler.start_sample(first_sample + taken);
+
+                let (jitter_x, jitter_y) = sampler.next_2d();
+                let fx = i as f32 + jitter_x;
+                let fy = j as f32 + jitter_y;
+                let u = fx / image_width as f32;
+                let v = fy / image_height as f32;
+
+                let r = camera.get_ray(u, v, sampler);
+                let c = if tile.aovs.is_some() {
+                    let (c, first_hit) = integrator.radiance_with_aov(r, world, lights, sampler);
+                    aov.add(first_hit);
+                    c
+                } else {
+                    integrator.radiance(r, world, lights, sampler)
+                };
+                taken += 1;
+
+                if !c.has_nans() {
+                    stats.add(c);
+                    tile.add_sample(fx, fy, c, filter);
+                } else {
+                    tile.add_sample(fx, fy, Vector3::zero(), filter);
+                }
+
+                if let Some(adaptive) = adaptive {
+                    if adaptive.converged(&stats) {
+                        break;
+                    }
+                }
+            }
+
+            tile.put_sample_count(i - x, j - y, taken);
+            tile.put_aov(i - x, j - y, aov.result());
+        }
+    }
+}
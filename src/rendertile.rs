@@ -1,25 +1,48 @@
-extern crate image;
+extern crate tdmath;
 
-use image::{ImageBuffer, Rgba};
+use tdmath::Vector3;
+use aov::AovPixel;
+use filter::Filter;
 
+// Region of the image rendered as a unit of work. Filtered samples spill over the edges of
+// the tile, so pixels and weights cover the tile grown by margin pixels on every side.
 pub struct RenderTile {
     x: u32,
     y: u32,
     width: u32,
     height: u32,
-    pub image: Box<ImageBuffer<Rgba<u8>, Vec<u8>>>,
+    margin: u32,
+    pub pixels: Vec<Vector3>,
+    pub weights: Vec<f32>,
+    // Sum of the magnitudes of the weights, for pixels where negative filter lobes cancel out
+    pub abs_weights: Vec<f32>,
+    pub sample_counts: Vec<u32>,
+    pub aovs: Option<Vec<AovPixel>>,
 }
 
 impl RenderTile {
     pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
-        let image = ImageBuffer::new(width, height);
+        RenderTile::with_margin(x, y, width, height, 0)
+    }
+
+    pub fn with_margin(x: u32, y: u32, width: u32, height: u32, margin: u32) -> Self {
+        let splat_size = ((width + 2 * margin) * (height + 2 * margin)) as usize;
+        let pixels = vec![Vector3::zero(); splat_size];
+        let weights = vec![0.0; splat_size];
+        let abs_weights = vec![0.0; splat_size];
+        let sample_counts = vec![0; (width * height) as usize];
 
         RenderTile {
             x,
             y,
             width,
             height,
-            image: Box::new(image),
+            margin,
+            pixels,
+            weights,
+            abs_weights,
+            sample_counts,
+            aovs: None,
         }
     }
 
@@ -38,4 +61,90 @@ impl RenderTile {
     pub fn height(&self) -> u32 {
         self.height
     }
-}
\ No newline at end of file
+
+    pub fn margin(&self) -> u32 {
+        self.margin
+    }
+
+    // Splats a sample at image position (fx, fy), measured in pixels, to the pixels around it
+    pub fn add_sample(&mut self, fx: f32, fy: f32, color: Vector3, filter: &Filter) {
+        let radius = filter.radius();
+        let x0 = (fx - 0.5 - radius).ceil() as i32;
+        let x1 = (fx - 0.5 + radius).floor() as i32;
+        let y0 = (fy - 0.5 - radius).ceil() as i32;
+        let y1 = (fy - 0.5 + radius).floor() as i32;
+
+        let splat_x = self.x as i32 - self.margin as i32;
+        let splat_y = self.y as i32 - self.margin as i32;
+        let splat_width = (self.width + 2 * self.margin) as i32;
+        let splat_height = (self.height + 2 * self.margin) as i32;
+
+        for py in y0..y1 + 1 {
+            for px in x0..x1 + 1 {
+                let lx = px - splat_x;
+                let ly = py - splat_y;
+                if lx < 0 || ly < 0 || lx >= splat_width || ly >= splat_height {
+                    continue;
+                }
+
+                let weight = filter.evaluate(px as f32 + 0.5 - fx, py as f32 + 0.5 - fy);
+                if weight == 0.0 {
+                    continue;
+                }
+
+                let index = (ly * splat_width + lx) as usize;
+                self.pixels[index] = self.pixels[index] + color * weight;
+                self.weights[index] += weight;
+                self.abs_weights[index] += weight.abs();
+            }
+        }
+    }
+
+    // Adds the weighted samples of this tile to image sized buffers, dropping any outside the image
+    pub fn splat_into(&self, pixels: &mut [Vector3], weights: &mut [f32], abs_weights: &mut [f32], image_width: u32, image_height: u32) {
+        let splat_width = self.width + 2 * self.margin;
+        let splat_height = self.height + 2 * self.margin;
+
+        for ly in 0..splat_height {
+            for lx in 0..splat_width {
+                let x = (self.x + lx) as i32 - self.margin as i32;
+                let y = (self.y + ly) as i32 - self.margin as i32;
+                if x < 0 || y < 0 || x >= image_width as i32 || y >= image_height as i32 {
+                    continue;
+                }
+
+                let src = (ly * splat_width + lx) as usize;
+                let dst = (y as u32 * image_width + x as u32) as usize;
+                pixels[dst] = pixels[dst] + self.pixels[src];
+                weights[dst] += self.weights[src];
+                abs_weights[dst] += self.abs_weights[src];
+            }
+        }
+    }
+
+    pub fn get_sample_count(&self, x: u32, y: u32) -> u32 {
+        self.sample_counts[(y * self.width + x) as usize]
+    }
+
+    pub fn put_sample_count(&mut self, x: u32, y: u32, count: u32) {
+        self.sample_counts[(y * self.width + x) as usize] = count;
+    }
+
+    pub fn enable_aovs(&mut self) {
+        self.aovs = Some(vec![AovPixel::new(); (self.width * self.height) as usize]);
+    }
+
+    pub fn get_aov(&self, x: u32, y: u32) -> Option<AovPixel> {
+        match self.aovs {
+            Some(ref aovs) => Some(aovs[(y * self.width + x) as usize]),
+            None => None,
+        }
+    }
+
+    pub fn put_aov(&mut self, x: u32, y: u32, aov: AovPixel) {
+        let index = (y * self.width + x) as usize;
+        if let Some(ref mut aovs) = self.aovs {
+            aovs[index] = aov;
+        }
+    }
+}
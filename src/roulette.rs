@@ -0,0 +1,75 @@
+extern crate tdmath;
+extern crate toml;
+
+use tdmath::Vector3;
+use toml::Value;
+
+// Path termination. Paths stop after max_depth bounces, and from start_depth bounces on they
+// are randomly stopped with a probability based on their throughput. Surviving paths are
+// divided by their survival probability so the image stays unbiased.
+#[derive(Debug, Copy, Clone)]
+pub struct RussianRoulette {
+    max_depth: u32,
+    start_depth: u32,
+}
+
+impl RussianRoulette {
+    pub fn new(max_depth: u32, start_depth: u32) -> Self {
+        RussianRoulette {
+            max_depth,
+            start_depth,
+        }
+    }
+
+    pub fn from_toml(render_data: &Value) -> Self {
+        let default = RussianRoulette::default();
+
+        let max_depth = match render_data.get("max_depth") {
+            Some(max_depth) => max_depth.as_integer().unwrap() as u32,
+            None => default.max_depth,
+        };
+
+        let start_depth = match render_data.get("rr_start_depth") {
+            Some(start_depth) => start_depth.as_integer().unwrap() as u32,
+            None => default.start_depth,
+        };
+
+        RussianRoulette::new(max_depth, start_depth)
+    }
+
+    pub fn max_depth(&self) -> u32 {
+        self.max_depth
+    }
+
+    pub fn start_depth(&self) -> u32 {
+        self.start_depth
+    }
+
+    pub fn set_max_depth(&mut self, max_depth: u32) {
+        self.max_depth = max_depth;
+    }
+
+    pub fn set_start_depth(&mut self, start_depth: u32) {
+        self.start_depth = start_depth;
+    }
+
+    // Chance of a path continuing after depth bounces with the given throughput
+    pub fn survival_probability(&self, depth: u32, throughput: Vector3) -> f32 {
+        if depth < self.start_depth {
+            return 1.0;
+        }
+
+        let max_component = throughput.x.max(throughput.y).max(throughput.z);
+        if max_component.is_nan() {
+            0.0
+        } else {
+            max_component.min(0.95).max(0.0)
+        }
+    }
+}
+
+impl Default for RussianRoulette {
+    fn default() -> Self {
+        RussianRoulette::new(50, 3)
+    }
+}
@@ -0,0 +1,78 @@
+use sampler::*;
+
+const PRIMES: [u32; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53,
+    59, 61, 67, 71, 73, 79, 83, 89, 97, 101, 103, 107, 109, 113, 127, 131,
+];
+
+// Halton sequence with a prime base per dimension. Every pixel shifts the sequence by a random
+// offset per dimension (Cranley-Patterson rotation) so that neighbouring pixels are not
+// correlated. Dimensions past the prime table fall back to independent random numbers.
+#[derive(Debug, Copy, Clone)]
+pub struct HaltonSampler {
+    seed: u32,
+    pixel_seed: u32,
+    index: u32,
+    dimension: u32,
+}
+
+impl HaltonSampler {
+    pub fn new(seed: u32) -> Self {
+        HaltonSampler {
+            seed,
+            pixel_seed: seed,
+            index: 0,
+            dimension: 0,
+        }
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn start_pixel(&mut self, x: u32, y: u32, _samples: u32) {
+        self.pixel_seed = hash(hash(self.seed, x), y);
+    }
+
+    fn start_sample(&mut self, index: u32) {
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn next_1d(&mut self) -> f32 {
+        let offset = hash(self.pixel_seed, self.dimension);
+        let value = if (self.dimension as usize) < PRIMES.len() {
+            let value = radical_inverse(PRIMES[self.dimension as usize], self.index) + to_unit_float(offset);
+            value - value.floor()
+        } else {
+            to_unit_float(hash(offset, self.index))
+        };
+        self.dimension += 1;
+
+        value.min(ONE_MINUS_EPSILON)
+    }
+
+    fn next_2d(&mut self) -> (f32, f32) {
+        let u = self.next_1d();
+        let v = self.next_1d();
+        (u, v)
+    }
+
+    fn clone_box(&self) -> Box<Sampler> {
+        Box::new(*self)
+    }
+}
+
+// Mirrors the digits of index in base around the radix point
+fn radical_inverse(base: u32, index: u32) -> f32 {
+    let inv_base = 1.0 / base as f64;
+    let mut factor = inv_base;
+    let mut result = 0.0;
+    let mut i = index;
+
+    while i > 0 {
+        result += (i % base) as f64 * factor;
+        i /= base;
+        factor *= inv_base;
+    }
+
+    result as f32
+}
@@ -0,0 +1,48 @@
+use sampler::*;
+
+// Uncorrelated random numbers for every dimension of every sample
+#[derive(Debug, Copy, Clone)]
+pub struct IndependentSampler {
+    seed: u32,
+    pixel_seed: u32,
+    sample_seed: u32,
+    dimension: u32,
+}
+
+impl IndependentSampler {
+    pub fn new(seed: u32) -> Self {
+        IndependentSampler {
+            seed,
+            pixel_seed: seed,
+            sample_seed: seed,
+            dimension: 0,
+        }
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn start_pixel(&mut self, x: u32, y: u32, _samples: u32) {
+        self.pixel_seed = hash(hash(self.seed, x), y);
+    }
+
+    fn start_sample(&mut self, index: u32) {
+        self.sample_seed = hash(self.pixel_seed, index);
+        self.dimension = 0;
+    }
+
+    fn next_1d(&mut self) -> f32 {
+        let value = to_unit_float(hash(self.sample_seed, self.dimension));
+        self.dimension += 1;
+        value
+    }
+
+    fn next_2d(&mut self) -> (f32, f32) {
+        let u = self.next_1d();
+        let v = self.next_1d();
+        (u, v)
+    }
+
+    fn clone_box(&self) -> Box<Sampler> {
+        Box::new(*self)
+    }
+}
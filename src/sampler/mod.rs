@@ -0,0 +1,115 @@
+pub mod independent;
+pub mod stratified;
+pub mod halton;
+pub mod sobol;
+
+pub use self::independent::IndependentSampler;
+pub use self::stratified::StratifiedSampler;
+pub use self::halton::HaltonSampler;
+pub use self::sobol::SobolSampler;
+
+use tdmath::Vector3;
+use std::f32::consts::PI;
+
+// Source of the random numbers used to trace a path. Every sample of a pixel draws its numbers
+// in the same order, one dimension per number, so that low discrepancy samplers can spread each
+// dimension evenly over the samples of the pixel.
+pub trait Sampler: Send {
+    // Begins pixel (x, y), which will take samples samples
+    fn start_pixel(&mut self, x: u32, y: u32, samples: u32);
+
+    // Begins sample index of the current pixel, restarting from the first dimension
+    fn start_sample(&mut self, index: u32);
+
+    fn next_1d(&mut self) -> f32;
+    fn next_2d(&mut self) -> (f32, f32);
+
+    fn clone_box(&self) -> Box<Sampler>;
+}
+
+pub fn from_name(name: &str, seed: u32) -> Option<Box<Sampler>> {
+    match name {
+        "independent" => Some(Box::new(IndependentSampler::new(seed))),
+        "stratified" => Some(Box::new(StratifiedSampler::new(seed))),
+        "halton" => Some(Box::new(HaltonSampler::new(seed))),
+        "sobol" => Some(Box::new(SobolSampler::new(seed))),
+        _ => None,
+    }
+}
+
+// Point on the unit disk in the xy plane using the concentric mapping
+pub fn sample_unit_disk(u: (f32, f32)) -> Vector3 {
+    let a = 2.0 * u.0 - 1.0;
+    let b = 2.0 * u.1 - 1.0;
+    if a == 0.0 && b == 0.0 {
+        return Vector3::zero();
+    }
+
+    let (r, theta) = if a.abs() > b.abs() {
+        (a, PI / 4.0 * (b / a))
+    } else {
+        (b, PI / 2.0 - PI / 4.0 * (a / b))
+    };
+
+    Vector3::new(r * theta.cos(), r * theta.sin(), 0.0)
+}
+
+// Point inside the unit sphere, u picks the direction and r the distance from the centre
+pub fn sample_in_unit_sphere(u: (f32, f32), r: f32) -> Vector3 {
+    let z = 1.0 - 2.0 * u.0;
+    let s = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * u.1;
+
+    Vector3::new(s * phi.cos(), s * phi.sin(), z) * r.cbrt()
+}
+
+// Cosine weighted direction around +z
+pub fn sample_cosine_direction(u: (f32, f32)) -> Vector3 {
+    let z = (1.0 - u.1).sqrt();
+    let phi = 2.0 * PI * u.0;
+    let s = u.1.sqrt();
+
+    Vector3::new(phi.cos() * s, phi.sin() * s, z)
+}
+
+// Direction around +z towards a sphere of radius at distance_squared from the origin
+pub fn sample_to_sphere(radius: f32, distance_squared: f32, u: (f32, f32)) -> Vector3 {
+    let cos_theta_max = (1.0 - radius * radius / distance_squared).max(0.0).sqrt();
+    let z = 1.0 + u.1 * (cos_theta_max - 1.0);
+    let phi = 2.0 * PI * u.0;
+    let s = (1.0 - z * z).max(0.0).sqrt();
+
+    Vector3::new(phi.cos() * s, phi.sin() * s, z)
+}
+
+// Scrambles the bits of x so that nearby inputs give unrelated outputs
+pub fn mix_bits(x: u32) -> u32 {
+    let mut x = x;
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb352d);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846ca68b);
+    x ^= x >> 16;
+    x
+}
+
+pub fn hash(seed: u32, value: u32) -> u32 {
+    mix_bits(seed ^ mix_bits(value.wrapping_add(0x9e3779b9)))
+}
+
+// Largest f32 below one
+pub const ONE_MINUS_EPSILON: f32 = 0.99999994;
+
+// Maps random bits to [0, 1)
+pub fn to_unit_float(x: u32) -> f32 {
+    (x >> 8) as f32 * (1.0 / 16777216.0)
+}
+
+pub fn reverse_bits(x: u32) -> u32 {
+    let mut x = x;
+    x = ((x >> 1) & 0x55555555) | ((x & 0x55555555) << 1);
+    x = ((x >> 2) & 0x33333333) | ((x & 0x33333333) << 2);
+    x = ((x >> 4) & 0x0f0f0f0f) | ((x & 0x0f0f0f0f) << 4);
+    x = ((x >> 8) & 0x00ff00ff) | ((x & 0x00ff00ff) << 8);
+    (x >> 16) | (x << 16)
+}
@@ -0,0 +1,88 @@
+use sampler::*;
+
+// Owen scrambled Sobol points following Burley's "Practical Hash-based Owen Scrambling".
+// Every 1D or 2D draw uses the first two Sobol dimensions with its own shuffle of the sample
+// index and its own scramble, so each draw stays well stratified over the samples of a pixel
+// without needing direction numbers for higher dimensions.
+#[derive(Debug, Copy, Clone)]
+pub struct SobolSampler {
+    seed: u32,
+    pixel_seed: u32,
+    index: u32,
+    dimension: u32,
+}
+
+impl SobolSampler {
+    pub fn new(seed: u32) -> Self {
+        SobolSampler {
+            seed,
+            pixel_seed: seed,
+            index: 0,
+            dimension: 0,
+        }
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn start_pixel(&mut self, x: u32, y: u32, _samples: u32) {
+        self.pixel_seed = hash(hash(self.seed, x), y);
+    }
+
+    fn start_sample(&mut self, index: u32) {
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn next_1d(&mut self) -> f32 {
+        let seed = hash(self.pixel_seed, self.dimension);
+        let index = nested_uniform_scramble(self.index, seed);
+        self.dimension += 1;
+
+        to_unit_float(nested_uniform_scramble(reverse_bits(index), hash(seed, 1)))
+    }
+
+    fn next_2d(&mut self) -> (f32, f32) {
+        let seed = hash(self.pixel_seed, self.dimension);
+        let index = nested_uniform_scramble(self.index, seed);
+        self.dimension += 1;
+
+        let x = nested_uniform_scramble(reverse_bits(index), hash(seed, 1));
+        let y = nested_uniform_scramble(sobol_dimension1(index), hash(seed, 2));
+        (to_unit_float(x), to_unit_float(y))
+    }
+
+    fn clone_box(&self) -> Box<Sampler> {
+        Box::new(*self)
+    }
+}
+
+// Second Sobol dimension, its generator matrix is the Pascal matrix mod 2
+fn sobol_dimension1(index: u32) -> u32 {
+    let mut v = 1 << 31;
+    let mut result = 0;
+    let mut i = index;
+
+    while i != 0 {
+        if i & 1 != 0 {
+            result ^= v;
+        }
+        i >>= 1;
+        v ^= v >> 1;
+    }
+
+    result
+}
+
+// Hash that only lets each bit be affected by the bits below it
+fn laine_karras_permutation(x: u32, seed: u32) -> u32 {
+    let mut x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+    x
+}
+
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    reverse_bits(laine_karras_permutation(reverse_bits(x), seed))
+}
@@ -0,0 +1,107 @@
+use sampler::*;
+
+// Jittered strata, one stratum per sample of a pixel. 1D dimensions are split into samples
+// strata and 2D dimensions into a square grid with at least samples cells. Each dimension
+// visits its strata in a different order so dimensions are not correlated.
+#[derive(Debug, Copy, Clone)]
+pub struct StratifiedSampler {
+    seed: u32,
+    pixel_seed: u32,
+    samples: u32,
+    index: u32,
+    dimension: u32,
+}
+
+impl StratifiedSampler {
+    pub fn new(seed: u32) -> Self {
+        StratifiedSampler {
+            seed,
+            pixel_seed: seed,
+            samples: 1,
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    // Seed for the current dimension. Samples past the pixel's sample count start a new set of strata.
+    fn dimension_seed(&self) -> u32 {
+        hash(hash(self.pixel_seed, self.dimension), self.index / self.samples)
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_pixel(&mut self, x: u32, y: u32, samples: u32) {
+        self.pixel_seed = hash(hash(self.seed, x), y);
+        self.samples = samples.max(1);
+    }
+
+    fn start_sample(&mut self, index: u32) {
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn next_1d(&mut self) -> f32 {
+        let seed = self.dimension_seed();
+        let stratum = permute(self.index % self.samples, self.samples, seed);
+        let jitter = to_unit_float(hash(seed, self.index));
+        self.dimension += 1;
+
+        ((stratum as f32 + jitter) / self.samples as f32).min(ONE_MINUS_EPSILON)
+    }
+
+    fn next_2d(&mut self) -> (f32, f32) {
+        let seed = self.dimension_seed();
+        let size = (self.samples as f32).sqrt().ceil() as u32;
+        let cells = size * size;
+        let cell = permute(self.index % self.samples, cells, seed);
+        let jitter_x = to_unit_float(hash(seed, self.index));
+        let jitter_y = to_unit_float(hash(seed ^ 0x5bd1e995, self.index));
+        self.dimension += 1;
+
+        let x = ((cell % size) as f32 + jitter_x) / size as f32;
+        let y = ((cell / size) as f32 + jitter_y) / size as f32;
+        (x.min(ONE_MINUS_EPSILON), y.min(ONE_MINUS_EPSILON))
+    }
+
+    fn clone_box(&self) -> Box<Sampler> {
+        Box::new(*self)
+    }
+}
+
+// Random permutation of 0..length without storing it, from Kensler's correlated multi-jittered sampling
+fn permute(index: u32, length: u32, seed: u32) -> u32 {
+    let mut w = length - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+
+    let mut i = index;
+    loop {
+        i ^= seed;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= seed >> 16;
+        i ^= (i & w) >> 4;
+        i ^= seed >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= seed >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | seed >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+
+        if i < length {
+            break;
+        }
+    }
+
+    i.wrapping_add(seed) % length
+}
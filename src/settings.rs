@@ -10,6 +10,29 @@ pub struct Settings {
     export_path: PathBuf,
     scene_path: PathBuf,
     threads: u32,
+    tile_size: u32,
+    progressive: bool,
+    pass_samples: u32,
+    preview_path: PathBuf,
+    preview_passes: u32,
+    preview_seconds: u32,
+    adaptive_threshold: f32,
+    min_samples: u32,
+    sample_map_path: Option<PathBuf>,
+    tone_mapper: Option<String>,
+    exposure: Option<f32>,
+    aovs: bool,
+    denoise: bool,
+    denoise_iterations: Option<u32>,
+    denoise_sigma_color: Option<f32>,
+    denoise_sigma_normal: Option<f32>,
+    denoise_sigma_depth: Option<f32>,
+    filter: Option<String>,
+    filter_radius: f32,
+    sampler: Option<String>,
+    seed: Option<u32>,
+    max_depth: Option<u32>,
+    rr_start_depth: Option<u32>,
 }
 
 impl Settings {
@@ -44,6 +67,121 @@ impl Settings {
             _ => 4,
         };
 
+        let tile_size = match commandline.get_parameter_value("tile_size") {
+            ParameterValue::UInteger(tile_size) => *tile_size,
+            _ => 32,
+        };
+
+        let progressive = match commandline.get_parameter_value("progressive") {
+            ParameterValue::Bool(progressive) => *progressive,
+            _ => false,
+        };
+
+        let pass_samples = match commandline.get_parameter_value("pass_samples") {
+            ParameterValue::UInteger(pass_samples) => *pass_samples,
+            _ => 4,
+        };
+
+        let preview = match commandline.get_parameter_value("preview") {
+            ParameterValue::Path(path) => PathBuf::from(path),
+            _ => output.clone(),
+        };
+
+        let preview_passes = match commandline.get_parameter_value("preview_passes") {
+            ParameterValue::UInteger(preview_passes) => *preview_passes,
+            _ => 1,
+        };
+
+        let preview_seconds = match commandline.get_parameter_value("preview_seconds") {
+            ParameterValue::UInteger(preview_seconds) => *preview_seconds,
+            _ => 0,
+        };
+
+        let adaptive_threshold = match commandline.get_parameter_value("adaptive") {
+            ParameterValue::Float(threshold) => *threshold,
+            _ => 0.0,
+        };
+
+        let min_samples = match commandline.get_parameter_value("min_samples") {
+            ParameterValue::UInteger(min_samples) => *min_samples,
+            _ => 16,
+        };
+
+        let sample_map = match commandline.get_parameter_value("sample_map") {
+            ParameterValue::Path(path) => Some(PathBuf::from(path)),
+            _ => None,
+        };
+
+        let tone_mapper = match commandline.get_parameter_value("tone_mapper") {
+            ParameterValue::String(tone_mapper) => Some(tone_mapper.clone()),
+            _ => None,
+        };
+
+        let exposure = match commandline.get_parameter_value("exposure") {
+            ParameterValue::Float(exposure) => Some(*exposure),
+            _ => None,
+        };
+
+        let aovs = match commandline.get_parameter_value("aovs") {
+            ParameterValue::Bool(aovs) => *aovs,
+            _ => false,
+        };
+
+        let denoise = match commandline.get_parameter_value("denoise") {
+            ParameterValue::Bool(denoise) => *denoise,
+            _ => false,
+        };
+
+        let denoise_iterations = match commandline.get_parameter_value("denoise_iterations") {
+            ParameterValue::UInteger(iterations) => Some(*iterations),
+            _ => None,
+        };
+
+        let denoise_sigma_color = match commandline.get_parameter_value("denoise_sigma_color") {
+            ParameterValue::Float(sigma) => Some(*sigma),
+            _ => None,
+        };
+
+        let denoise_sigma_normal = match commandline.get_parameter_value("denoise_sigma_normal") {
+            ParameterValue::Float(sigma) => Some(*sigma),
+            _ => None,
+        };
+
+        let denoise_sigma_depth = match commandline.get_parameter_value("denoise_sigma_depth") {
+            ParameterValue::Float(sigma) => Some(*sigma),
+            _ => None,
+        };
+
+        let filter = match commandline.get_parameter_value("filter") {
+            ParameterValue::String(filter) => Some(filter.clone()),
+            _ => None,
+        };
+
+        let filter_radius = match commandline.get_parameter_value("filter_radius") {
+            ParameterValue::Float(filter_radius) => *filter_radius,
+            _ => 0.0,
+        };
+
+        let sampler = match commandline.get_parameter_value("sampler") {
+            ParameterValue::String(sampler) => Some(sampler.clone()),
+            _ => None,
+        };
+
+        let seed = match commandline.get_parameter_value("seed") {
+            ParameterValue::UInteger(seed) => Some(*seed),
+            _ => None,
+        };
+
+        let max_depth = match commandline.get_parameter_value("max_depth") {
+            ParameterValue::UInteger(max_depth) => Some(*max_depth),
+            _ => None,
+        };
+
+        let rr_start_depth = match commandline.get_parameter_value("rr_start_depth") {
+            ParameterValue::UInteger(rr_start_depth) => Some(*rr_start_depth),
+            _ => None,
+        };
+
         Settings {
             width,
             height,
@@ -51,6 +189,29 @@ impl Settings {
             export_path: output,
             scene_path: scene,
             threads,
+            tile_size,
+            progressive,
+            pass_samples,
+            preview_path: preview,
+            preview_passes,
+            preview_seconds,
+            adaptive_threshold,
+            min_samples,
+            sample_map_path: sample_map,
+            tone_mapper,
+            exposure,
+            aovs,
+            denoise,
+            denoise_iterations,
+            denoise_sigma_color,
+            denoise_sigma_normal,
+            denoise_sigma_depth,
+            filter,
+            filter_radius,
+            sampler,
+            seed,
+            max_depth,
+            rr_start_depth,
         }
     }
 
@@ -77,4 +238,102 @@ impl Settings {
     pub fn threads(&self) -> u32 {
         self.threads
     }
+
+    pub fn tile_size(&self) -> u32 {
+        self.tile_size
+    }
+
+    pub fn progressive(&self) -> bool {
+        self.progressive
+    }
+
+    pub fn pass_samples(&self) -> u32 {
+        self.pass_samples
+    }
+
+    pub fn preview_path(&self) -> &PathBuf {
+        &self.preview_path
+    }
+
+    pub fn preview_passes(&self) -> u32 {
+        self.preview_passes
+    }
+
+    pub fn preview_seconds(&self) -> u32 {
+        self.preview_seconds
+    }
+
+    pub fn adaptive_threshold(&self) -> f32 {
+        self.adaptive_threshold
+    }
+
+    pub fn min_samples(&self) -> u32 {
+        self.min_samples
+    }
+
+    pub fn sample_map_path(&self) -> Option<&PathBuf> {
+        self.sample_map_path.as_ref()
+    }
+
+    // Tone mapping and exposure override the scene's [film] table when given
+    pub fn tone_mapper(&self) -> Option<&String> {
+        self.tone_mapper.as_ref()
+    }
+
+    pub fn exposure(&self) -> Option<f32> {
+        self.exposure
+    }
+
+    pub fn aovs(&self) -> bool {
+        self.aovs
+    }
+
+    pub fn denoise(&self) -> bool {
+        self.denoise
+    }
+
+    // Denoiser settings override the scene's [film] table when given
+    pub fn denoise_iterations(&self) -> Option<u32> {
+        self.denoise_iterations
+    }
+
+    pub fn denoise_sigma_color(&self) -> Option<f32> {
+        self.denoise_sigma_color
+    }
+
+    pub fn denoise_sigma_normal(&self) -> Option<f32> {
+        self.denoise_sigma_normal
+    }
+
+    pub fn denoise_sigma_depth(&self) -> Option<f32> {
+        self.denoise_sigma_depth
+    }
+
+    // The filter overrides the scene's [film] table when given
+    pub fn filter(&self) -> Option<&String> {
+        self.filter.as_ref()
+    }
+
+    // Applies to the filter from --filter, the scene or the default, zero or less keeps its radius
+    pub fn filter_radius(&self) -> f32 {
+        self.filter_radius
+    }
+
+    pub fn sampler(&self) -> Option<&String> {
+        self.sampler.as_ref()
+    }
+
+    // Renders are only reproducible when a seed is given
+    pub fn seed(&self) -> Option<u32> {
+        self.seed
+    }
+
+    // Path depth settings override the scene's [render] table when given
+    pub fn max_depth(&self) -> Option<u32> {
+        self.max_depth
+    }
+
+    pub fn rr_start_depth(&self) -> Option<u32> {
+        self.rr_start_depth
+    }
 }
\ No newline at end of file
@@ -0,0 +1,171 @@
+extern crate tdmath;
+
+use tdmath::Vector3;
+use std::f32::consts::PI;
+
+// Angular radius of the sun disk in radians
+pub const SUN_ANGULAR_RADIUS: f32 = 0.004_65;
+
+// Luminance of the sun outside the atmosphere in kcd/m^2
+const SUN_LUMINANCE: f32 = 1.6e6;
+
+// Brings luminance in kcd/m^2 to values around 1 for a clear daytime sky
+const SKY_SCALE: f32 = 0.05;
+
+// Preetham's analytic daylight model. The sky colour depends on the sun position and the
+// turbidity, the haziness of the atmosphere from 2 (very clear) to 10 (hazy). Below the horizon
+// is a flat ground lit by the sun and sky.
+pub struct Sky {
+    sun_direction: Vector3,
+    // Perez distribution coefficients for the luminance Y and chromaticity x and y
+    perez: [[f32; 5]; 3],
+    // Yxy at the zenith, divided by the Perez distribution there so atmosphere only has to
+    // evaluate it in the direction asked for
+    zenith: [f32; 3],
+    sun_radiance: Vector3,
+    ground: Vector3,
+    intensity: f32,
+}
+
+impl Sky {
+    // sun_direction points towards the sun, intensity scales the radiance of the sky and sun
+    pub fn new(sun_direction: Vector3, turbidity: f32, ground_albedo: Vector3, intensity: f32) -> Self {
+        let sun_direction = sun_direction.normalized();
+        let t = turbidity;
+
+        // The model breaks down once the sun sets
+        let theta_s = sun_direction.y.max(0.0).min(1.0).acos().min(PI / 2.0 - 0.001);
+
+        let perez = [
+            [0.1787 * t - 1.4630, -0.3554 * t + 0.4275, -0.0227 * t + 5.3251, 0.1206 * t - 2.5771, -0.0670 * t + 0.3703],
+            [-0.0193 * t - 0.2592, -0.0665 * t + 0.0008, -0.0004 * t + 0.2125, -0.0641 * t - 0.8989, -0.0033 * t + 0.0452],
+            [-0.0167 * t - 0.2608, -0.0950 * t + 0.0092, -0.0079 * t + 0.2102, -0.0441 * t - 1.6537, -0.0109 * t + 0.0529],
+        ];
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+
+        let theta2 = theta_s * theta_s;
+        let theta3 = theta2 * theta_s;
+        let zenith_x = t * t * (0.00166 * theta3 - 0.00375 * theta2 + 0.00209 * theta_s)
+            + t * (-0.02903 * theta3 + 0.06377 * theta2 - 0.03202 * theta_s + 0.00394)
+            + (0.11693 * theta3 - 0.21196 * theta2 + 0.06052 * theta_s + 0.25886);
+        let zenith_y = t * t * (0.00275 * theta3 - 0.00610 * theta2 + 0.00317 * theta_s)
+            + t * (-0.04214 * theta3 + 0.08970 * theta2 - 0.04153 * theta_s + 0.00516)
+            + (0.15346 * theta3 - 0.26756 * theta2 + 0.06670 * theta_s + 0.26688);
+
+        let zenith = [zenith_luminance, zenith_x, zenith_y];
+        let mut scaled_zenith = [0.0; 3];
+        for i in 0..3 {
+            scaled_zenith[i] = zenith[i] / self::perez(perez[i], 1.0, theta_s.cos());
+        }
+
+        let mut sky = Sky {
+            sun_direction,
+            perez,
+            zenith: scaled_zenith,
+            sun_radiance: Sky::sun_radiance_through_atmosphere(theta_s, t) * intensity,
+            ground: Vector3::zero(),
+            intensity,
+        };
+
+        if sun_direction.y <= 0.0 {
+            sky.sun_radiance = Vector3::zero();
+        }
+
+        // Light reaching the ground from the sun and the upper hemisphere of the sky
+        let sun_solid_angle = 4.0 * PI * (0.5 * SUN_ANGULAR_RADIUS).sin().powi(2);
+        let mut irradiance = sky.sun_radiance * sun_solid_angle * sun_direction.y.max(0.0);
+        let steps = 64;
+        for i in 0..steps {
+            let theta = 0.5 * PI * (i as f32 + 0.5) / steps as f32;
+            for j in 0..(4 * steps) {
+                let phi = 2.0 * PI * (j as f32 + 0.5) / (4 * steps) as f32;
+                let direction = Vector3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin());
+                let d_omega = (0.5 * PI / steps as f32) * (2.0 * PI / (4 * steps) as f32) * theta.sin();
+                irradiance = irradiance + sky.atmosphere(direction) * theta.cos() * d_omega;
+            }
+        }
+
+        sky.ground = Vector3::new(ground_albedo.x * irradiance.x,
+                                  ground_albedo.y * irradiance.y,
+                                  ground_albedo.z * irradiance.z) / PI;
+        sky
+    }
+
+    pub fn sun_direction(&self) -> Vector3 {
+        self.sun_direction
+    }
+
+    // Radiance of the sun disk, zero once the sun has set
+    pub fn sun_radiance(&self) -> Vector3 {
+        self.sun_radiance
+    }
+
+    // Radiance arriving from direction including the sun disk
+    pub fn radiance(&self, direction: Vector3) -> Vector3 {
+        let direction = direction.normalized();
+        let sky = self.sky_radiance(direction);
+
+        if direction.y >= 0.0 && Vector3::dot(direction, self.sun_direction) >= SUN_ANGULAR_RADIUS.cos() {
+            sky + self.sun_radiance
+        } else {
+            sky
+        }
+    }
+
+    // Radiance arriving from direction without the sun disk
+    pub fn sky_radiance(&self, direction: Vector3) -> Vector3 {
+        let direction = direction.normalized();
+
+        if direction.y >= 0.0 {
+            self.atmosphere(direction)
+        } else {
+            self.ground
+        }
+    }
+
+    fn atmosphere(&self, direction: Vector3) -> Vector3 {
+        let cos_theta = direction.y.max(0.001);
+        let cos_gamma = Vector3::dot(direction, self.sun_direction).max(-1.0).min(1.0);
+
+        let mut yxy = [0.0; 3];
+        for i in 0..3 {
+            yxy[i] = self.zenith[i] * perez(self.perez[i], cos_theta, cos_gamma);
+        }
+
+        let luminance = yxy[0] * SKY_SCALE * self.intensity;
+        let (x, y) = (yxy[1], yxy[2]);
+        let cx = x / y * luminance;
+        let cz = (1.0 - x - y) / y * luminance;
+
+        Vector3::new((3.2406 * cx - 1.5372 * luminance - 0.4986 * cz).max(0.0),
+                     (-0.9689 * cx + 1.8758 * luminance + 0.0415 * cz).max(0.0),
+                     (0.0557 * cx - 0.2040 * luminance + 1.0570 * cz).max(0.0))
+    }
+
+    // Sunlight dimmed by Rayleigh and aerosol scattering on its way through the atmosphere
+    fn sun_radiance_through_atmosphere(theta_s: f32, turbidity: f32) -> Vector3 {
+        // Relative optical air mass
+        let air_mass = 1.0 / (theta_s.cos() + 0.15 * (93.885 - theta_s.to_degrees()).powf(-1.253));
+        let beta = 0.04608 * turbidity - 0.04586;
+
+        // Red, green and blue wavelengths in micrometres
+        let wavelengths = [0.68, 0.55, 0.44];
+        let mut transmittance = [0.0; 3];
+        for i in 0..3 {
+            let lambda: f32 = wavelengths[i];
+            let rayleigh = 0.008735 * lambda.powf(-4.08);
+            let aerosol = beta * lambda.powf(-1.3);
+            transmittance[i] = (-air_mass * (rayleigh + aerosol)).exp();
+        }
+
+        Vector3::new(transmittance[0], transmittance[1], transmittance[2]) * SUN_LUMINANCE * SKY_SCALE
+    }
+}
+
+// Perez sky luminance distribution
+fn perez(p: [f32; 5], cos_theta: f32, cos_gamma: f32) -> f32 {
+    let gamma = cos_gamma.acos();
+    (1.0 + p[0] * (p[1] / cos_theta).exp()) * (1.0 + p[2] * (p[3] * gamma).exp() + p[4] * cos_gamma * cos_gamma)
+}
@@ -7,6 +7,11 @@ use image::{ImageBuffer, Rgb};
 
 pub trait Texture: Send + Sync {
     fn value(&self, u: f32, v: f32, p: Vector3) -> Vector3;
+
+    // Materials read scalar parameters such as roughness from the red channel
+    fn scalar(&self, u: f32, v: f32, p: Vector3) -> f32 {
+        self.value(u, v, p).x
+    }
 }
 
 pub struct ConstantTexture {
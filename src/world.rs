@@ -1,35 +1,138 @@
 extern crate tdmath;
 extern crate toml;
-extern crate rand;
 
 use hitable::*;
 use tdmath::{Ray, Vector3};
 use aabb::AABB;
+use bvh::BVH;
 use self::toml::Value;
 use material::*;
 use texture::*;
 use noise::Perlin;
+use sampler::{Sampler, hash};
+use light::{Light, LightList, AreaLight, PointLight, SpotLight, DirectionalLight, EnvironmentLight, SunLight, luminance};
+use environment::EnvironmentMap;
+use sky::{Sky, SUN_ANGULAR_RADIUS};
+use std::sync::Arc;
 use transform::{Translate, RotateY};
-use rand::Rng;
 use std::path::Path;
 
 pub struct World {
-    hitables: Vec<Box<Hitable>>,
+    bvh: Option<BVH>,
+    unbounded: Vec<Box<Hitable>>,
+    bounded_ids: Vec<usize>,
+    unbounded_ids: Vec<usize>,
+    material_ids: Vec<usize>,
+    // Emissive objects from from_toml that can be sampled, and their emission
+    emitters: Vec<(Arc<Hitable>, Vector3)>,
     ambient_color: AmbientColor,
 }
 
 impl World {
-    pub fn from_toml(scene: &Value) -> Self {
+    pub fn new(hitables: Vec<Box<Hitable>>, ambient_color: AmbientColor) -> Self {
+        let material_ids = (0..hitables.len()).collect();
+        World::with_material_ids(hitables, material_ids, ambient_color)
+    }
+
+    // Objects with a bounding box are placed in a BVH, anything else is tested linearly.
+    // Objects are identified by their position in hitables, material_ids gives the material of each.
+    pub fn with_material_ids(hitables: Vec<Box<Hitable>>, material_ids: Vec<usize>, ambient_color: AmbientColor) -> Self {
+        let mut bounded: Vec<Box<Hitable>> = Vec::new();
+        let mut unbounded: Vec<Box<Hitable>> = Vec::new();
+        let mut bounded_ids = Vec::new();
+        let mut unbounded_ids = Vec::new();
+
+        for (id, hitable) in hitables.into_iter().enumerate() {
+            if hitable.bounding_box(0.0, 1.0).is_some() {
+                bounded.push(hitable);
+                bounded_ids.push(id);
+            } else {
+                unbounded.push(hitable);
+                unbounded_ids.push(id);
+            }
+        }
+
+        let bvh = if bounded.len() > 0 {
+            Some(BVH::from_list(bounded, 0.0, 1.0))
+        } else {
+            None
+        };
+
+        World {
+            bvh,
+            unbounded,
+            bounded_ids,
+            unbounded_ids,
+            material_ids,
+            emitters: Vec::new(),
+            ambient_color,
+        }
+    }
+
+    // Closest hit along with the object id of the object that was hit
+    pub fn hit_object(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<(HitRecord, usize)> {
+        let mut hit_record: Option<(HitRecord, usize)> = None;
+        let mut closest_so_far = t_max;
+
+        if let Some(ref bvh) = self.bvh {
+            if let Some((hit, index)) = bvh.hit_with_index(ray, t_min, closest_so_far) {
+                closest_so_far = hit.t();
+                hit_record = Some((hit, self.bounded_ids[index]));
+            }
+        }
+
+        for (hitable, &id) in self.unbounded.iter().zip(self.unbounded_ids.iter()) {
+            match hitable.hit(ray, t_min, closest_so_far) {
+                Some(hit) => {
+                    closest_so_far = hit.t();
+                    hit_record = Some((hit, id));
+                },
+                None => ()
+            }
+        }
+
+        hit_record
+    }
+
+    pub fn material_id(&self, object_id: usize) -> usize {
+        self.material_ids[object_id]
+    }
+
+    pub fn len(&self) -> usize {
+        let bounded = match self.bvh {
+            Some(ref bvh) => bvh.len(),
+            None => 0,
+        };
+
+        bounded + self.unbounded.len()
+    }
+
+    // seed picks the tables of procedural textures
+    pub fn from_toml(scene: &Value, seed: u32) -> Self {
         let mut hitables: Vec<Box<Hitable>> = Vec::new();
+        let mut material_ids = Vec::new();
+        let mut emitters = Vec::new();
 
         let objects = scene["objects"].as_table().unwrap();
+        let materials = scene["materials"].as_table().unwrap();
         for (_, obj_data) in objects.iter() {
             let obj_type = obj_data["type"].as_str().unwrap();
             let material_name = obj_data["material"].as_str().unwrap();
-            let material_data = &scene["materials"].as_table().unwrap()[material_name];
-
-            let hitable = World::create_object_from_toml(obj_type, obj_data, material_data, &scene["textures"]);
+            let material_data = &materials[material_name];
+
+            let hitable = World::create_object_from_toml(obj_type, obj_data, material_data, &scene["textures"], seed);
+            let hitable: Box<Hitable> = match World::area_light_emission(&*hitable, material_data, &scene["textures"], seed) {
+                Some(emission) => {
+                    let shared: Arc<Hitable> = Arc::from(hitable);
+                    emitters.push((Arc::clone(&shared), emission));
+                    Box::new(shared)
+                },
+                None => hitable,
+            };
             hitables.push(hitable);
+
+            let material_id = materials.keys().position(|name| name == material_name).unwrap();
+            material_ids.push(material_id);
         }
 
         let ambient_color = match scene.get("world") {
@@ -44,63 +147,200 @@ impl World {
             None => AmbientColor::Constant(Vector3::zero())
         };
 
-        World {
-            hitables,
-            ambient_color,
+        let mut world = World::with_material_ids(hitables, material_ids, ambient_color);
+        world.emitters = emitters;
+        world
+    }
+
+    // Emission of an object as an area light, None if its material doesn't emit or the object
+    // can't be sampled by a light
+    fn area_light_emission(hitable: &Hitable, material_data: &Value, textures: &Value, seed: u32) -> Option<Vector3> {
+        if !hitable.can_sample() {
+            return None;
+        }
+
+        let material = World::create_material_from_toml(material_data, textures, seed);
+        if !material.is_emissive() {
+            return None;
+        }
+
+        let emission = match hitable.bounding_box(0.0, 1.0) {
+            Some(bbox) => material.emission(&bbox),
+            None => material.emission(&AABB::new(Vector3::zero(), Vector3::zero())),
+        };
+
+        if emission.x > 0.0 || emission.y > 0.0 || emission.z > 0.0 {
+            Some(emission)
+        } else {
+            None
         }
     }
 
-    pub fn from_toml_samples(scene: &Value) -> Self {
-        let mut hitables: Vec<Box<Hitable>> = Vec::new();
+    // Every emissive object that can be sampled becomes an area light sharing the world's
+    // geometry, and every entry of the [lights] table a point, spot or directional light. self is
+    // the world built from the same scene, its size is used to weigh directional lights against
+    // the others.
+    pub fn lights_from_toml(&self, scene: &Value) -> LightList {
+        let mut lights: Vec<Box<Light>> = Vec::new();
 
-        let objects = scene["objects"].as_table().unwrap();
-        for (_, obj_data) in objects.iter() {
-            let obj_type = obj_data["type"].as_str().unwrap();
-            let material_name = obj_data["material"].as_str().unwrap();
-            let material_data = &scene["materials"].as_table().unwrap()[material_name];
-            let material = World::create_material_from_toml(material_data, &scene["textures"]);
+        for &(ref hitable, emission) in &self.emitters {
+            lights.push(Box::new(AreaLight::new(Arc::clone(hitable), emission)));
+        }
 
-            if material.sample() {
-                let hitable = World::create_object_from_toml(obj_type, obj_data, material_data, &scene["textures"]);
-                hitables.push(hitable);
+        let scene_radius = match self.bvh {
+            Some(ref bvh) => {
+                let bbox = bvh.bounding_box(0.0, 1.0).unwrap();
+                (bbox.max() - bbox.min()).length() / 2.0
+            },
+            None => 1.0,
+        };
+
+        if let AmbientColor::Environment(ref map) = self.ambient_color {
+            lights.push(Box::new(EnvironmentLight::new(Arc::clone(map), scene_radius)));
+        }
+
+        if let AmbientColor::Sky(ref sky) = self.ambient_color {
+            // The sky is tabulated for importance sampling, the sun disk is sampled on its own
+            let map = EnvironmentMap::from_radiance(256, 128, |direction| sky.sky_radiance(direction));
+            lights.push(Box::new(EnvironmentLight::new(Arc::new(map), scene_radius)));
+
+            if luminance(sky.sun_radiance()) > 0.0 {
+                lights.push(Box::new(SunLight::new(sky.sun_direction(), sky.sun_radiance(), SUN_ANGULAR_RADIUS, scene_radius)));
             }
         }
 
-        World {
-            hitables,
-            ambient_color: AmbientColor::Constant(Vector3::zero()), // Not needed for samples world
+        if let Some(light_table) = scene.get("lights") {
+            for (_, light_data) in light_table.as_table().unwrap().iter() {
+                lights.push(World::create_light_from_toml(light_data, scene_radius));
+            }
         }
+
+        LightList::new(lights)
     }
 
-    fn create_material_from_toml(material_data: &Value, textures: &Value) -> Box<Material> {
+    fn create_light_from_toml(light_data: &Value, scene_radius: f32) -> Box<Light> {
+        let light_type = light_data["type"].as_str().unwrap();
+
+        if light_type == "point" {
+            let position = vector_from_toml(&light_data["position"]);
+            let intensity = vector_from_toml(&light_data["intensity"]);
+            Box::new(PointLight::new(position, intensity))
+        } else if light_type == "spot" {
+            let position = vector_from_toml(&light_data["position"]);
+            let look = vector_from_toml(&light_data["look"]);
+            let intensity = vector_from_toml(&light_data["intensity"]);
+            let angle = light_data["angle"].as_float().unwrap() as f32;
+            let falloff = match light_data.get("falloff") {
+                Some(falloff) => falloff.as_float().unwrap() as f32,
+                None => angle,
+            };
+            Box::new(SpotLight::new(position, look, intensity, angle, falloff))
+        } else if light_type == "directional" {
+            let direction = vector_from_toml(&light_data["direction"]);
+            let irradiance = vector_from_toml(&light_data["irradiance"]);
+            Box::new(DirectionalLight::new(direction, irradiance, scene_radius))
+        } else {
+            panic!("Unknown light type")
+        }
+    }
+
+    fn create_material_from_toml(material_data: &Value, textures: &Value, seed: u32) -> Box<Material> {
         let material_type = material_data["type"].as_str().unwrap();
         
         if material_type == "lambertian" {
-            let texture_name = material_data["texture"].as_str().unwrap();
-            let texture_data = &textures[texture_name];
-            let texture = World::create_texture_from_toml(texture_data);
+            let texture = World::create_texture_parameter_from_toml(&material_data["texture"], textures, seed);
             Box::new(Lambertian::new(texture))
         } else if material_type == "dielectric" {
-            let ref_index = material_data["ref_index"].as_float().unwrap() as f32;
-            Box::new(Dielectric::new(ref_index))
+            let ref_index = World::create_texture_parameter_from_toml(&material_data["ref_index"], textures, seed);
+            let mut dielectric = Dielectric::new(ref_index);
+
+            if let Some(roughness) = material_data.get("roughness") {
+                dielectric.set_roughness(World::create_texture_parameter_from_toml(roughness, textures, seed));
+            }
+
+            if let Some(transmission) = material_data.get("transmission") {
+                dielectric.set_transmission(World::create_texture_parameter_from_toml(transmission, textures, seed));
+            }
+
+            if let Some(absorption) = material_data.get("absorption") {
+                dielectric.set_absorption(World::create_texture_parameter_from_toml(absorption, textures, seed));
+            }
+
+            Box::new(dielectric)
         } else if material_type == "metal" {
-            let albedo = material_data["albedo"].as_array().unwrap();
-            let r = albedo[0].as_float().unwrap() as f32;
-            let g = albedo[1].as_float().unwrap() as f32;
-            let b = albedo[2].as_float().unwrap() as f32;
-            let fuzz = material_data["fuzz"].as_float().unwrap() as f32;
-            Box::new(Metal::new(Vector3::new(r, g, b), fuzz))
+            // fuzz is the roughness of older scenes
+            let roughness = match material_data.get("roughness").or(material_data.get("fuzz")) {
+                Some(roughness) => World::create_texture_parameter_from_toml(roughness, textures, seed),
+                None => Box::new(ConstantTexture::new(Vector3::zero())),
+            };
+            let anisotropy = match material_data.get("anisotropy") {
+                Some(anisotropy) => World::create_texture_parameter_from_toml(anisotropy, textures, seed),
+                None => Box::new(ConstantTexture::new(Vector3::zero())),
+            };
+
+            if let Some(preset) = material_data.get("preset") {
+                let preset = preset.as_str().unwrap();
+                Box::new(Metal::from_preset(preset, roughness, anisotropy).expect("Unknown metal preset"))
+            } else if let Some(eta) = material_data.get("eta") {
+                let eta = World::create_texture_parameter_from_toml(eta, textures, seed);
+                let k = World::create_texture_parameter_from_toml(&material_data["k"], textures, seed);
+                Box::new(Metal::new(eta, k, roughness, anisotropy))
+            } else {
+                let albedo = World::create_texture_parameter_from_toml(&material_data["albedo"], textures, seed);
+                Box::new(Metal::from_albedo(albedo, roughness, anisotropy))
+            }
+        } else if material_type == "principled" {
+            let base_color = match material_data.get("base_color") {
+                Some(base_color) => World::create_texture_parameter_from_toml(base_color, textures, seed),
+                None => Box::new(ConstantTexture::new(Vector3::new(0.8, 0.8, 0.8))),
+            };
+            let mut principled = Principled::new(base_color);
+
+            if let Some(metallic) = material_data.get("metallic") {
+                principled.set_metallic(World::create_texture_parameter_from_toml(metallic, textures, seed));
+            }
+
+            if let Some(roughness) = material_data.get("roughness") {
+                principled.set_roughness(World::create_texture_parameter_from_toml(roughness, textures, seed));
+            }
+
+            if let Some(specular) = material_data.get("specular") {
+                principled.set_specular(World::create_texture_parameter_from_toml(specular, textures, seed));
+            }
+
+            if let Some(clearcoat) = material_data.get("clearcoat") {
+                principled.set_clearcoat(World::create_texture_parameter_from_toml(clearcoat, textures, seed));
+            }
+
+            if let Some(sheen) = material_data.get("sheen") {
+                principled.set_sheen(World::create_texture_parameter_from_toml(sheen, textures, seed));
+            }
+
+            if let Some(transmission) = material_data.get("transmission") {
+                principled.set_transmission(World::create_texture_parameter_from_toml(transmission, textures, seed));
+            }
+
+            Box::new(principled)
         } else if material_type == "diffuse_light" {
-            let texture_name = material_data["texture"].as_str().unwrap();
-            let texture_data = &textures[texture_name];
-            let texture = World::create_texture_from_toml(texture_data);
+            let texture = World::create_texture_parameter_from_toml(&material_data["texture"], textures, seed);
             Box::new(DiffuseLight::new(texture))
         } else {
             panic!("Unknown material type")
         }
     }
 
-    fn create_texture_from_toml(texture_data: &Value) -> Box<Texture> {
+    // Material parameter given as a number, a colour or the name of a texture
+    fn create_texture_parameter_from_toml(value: &Value, textures: &Value, seed: u32) -> Box<Texture> {
+        match *value {
+            Value::Float(value) => Box::new(ConstantTexture::new(Vector3::new(value as f32, value as f32, value as f32))),
+            Value::Integer(value) => Box::new(ConstantTexture::new(Vector3::new(value as f32, value as f32, value as f32))),
+            Value::Array(_) => Box::new(ConstantTexture::new(vector_from_toml(value))),
+            Value::String(ref texture_name) => World::create_texture_from_toml(&textures[texture_name.as_str()], hash_str(seed, texture_name)),
+            _ => panic!("Material parameters must be a number, a colour or a texture name"),
+        }
+    }
+
+    fn create_texture_from_toml(texture_data: &Value, seed: u32) -> Box<Texture> {
         let texture_type = texture_data["type"].as_str().unwrap();
 
         if texture_type == "constant" {
@@ -110,7 +350,7 @@ impl World {
             let b = color[2].as_float().unwrap() as f32;
             Box::new(ConstantTexture::new(Vector3::new(r, g, b)))
         } else if texture_type == "perlin" {
-            let perlin = Perlin::new();
+            let perlin = Perlin::new(seed);
             let scale = texture_data["scale"].as_float().unwrap() as f32;
             let turbulence = texture_data["turbulence"].as_integer().unwrap() as u32;
             Box::new(NoiseTexture::new(Box::new(perlin), scale, turbulence))
@@ -119,7 +359,7 @@ impl World {
         }
     }
 
-    fn create_object_from_toml(obj_type: &str, obj_data: &Value, material_data: &Value, textures: &Value) -> Box<Hitable> {
+    fn create_object_from_toml(obj_type: &str, obj_data: &Value, material_data: &Value, textures: &Value, seed: u32) -> Box<Hitable> {
         if obj_type == "sphere" {
             let position = obj_data["position"].as_array().unwrap();
             let x = position[0].as_float().unwrap() as f32;
@@ -127,7 +367,7 @@ impl World {
             let z = position[2].as_float().unwrap() as f32;
             let radius = obj_data["radius"].as_float().unwrap() as f32;
             
-            let material = World::create_material_from_toml(material_data, textures);
+            let material = World::create_material_from_toml(material_data, textures, seed);
             let sphere: Box<Hitable> = Box::new(Sphere::new(Vector3::new(x, y, z), radius, material));
 
             sphere
@@ -139,7 +379,7 @@ impl World {
             let y1 = bounds[3].as_float().unwrap() as f32;
             let k = obj_data["k"].as_float().unwrap() as f32;
 
-            let material = World::create_material_from_toml(material_data, textures);
+            let material = World::create_material_from_toml(material_data, textures, seed);
             let rect = XYRect::new(x0, x1, y0, y1, k, material);
             let flip = obj_data["flip"].as_bool().unwrap_or(false);
             if flip {
@@ -157,7 +397,7 @@ impl World {
             let z1 = bounds[3].as_float().unwrap() as f32;
             let k = obj_data["k"].as_float().unwrap() as f32;
 
-            let material = World::create_material_from_toml(material_data, textures);
+            let material = World::create_material_from_toml(material_data, textures, seed);
             let rect = XZRect::new(x0, x1, z0, z1, k, material);
             
             let flip = obj_data["flip"].as_bool().unwrap_or(false);
@@ -176,7 +416,7 @@ impl World {
             let z1 = bounds[3].as_float().unwrap() as f32;
             let k = obj_data["k"].as_float().unwrap() as f32;
 
-            let material = World::create_material_from_toml(material_data, textures);
+            let material = World::create_material_from_toml(material_data, textures, seed);
             let rect = YZRect::new(y0, y1, z0, z1, k, material);
 
             let flip = obj_data["flip"].as_bool().unwrap_or(false);
@@ -203,7 +443,7 @@ impl World {
 
             let mut materials = Vec::new();
             for _ in 0..6 {
-                materials.push(World::create_material_from_toml(material_data, textures));
+                materials.push(World::create_material_from_toml(material_data, textures, seed));
             }
             let cube = Box::new(Cube::new(min, max, &mut materials));
 
@@ -247,11 +487,11 @@ impl World {
             let z = v2[2].as_float().unwrap() as f32;
             let v2 = Vector3::new(x, y, z);
 
-            let material = World::create_material_from_toml(material_data, textures);
+            let material = World::create_material_from_toml(material_data, textures, seed);
             Box::new(Triangle::new(v0, v1, v2, material))
         } else if obj_type == "mesh" {
             let mesh_path = obj_data["path"].as_str().unwrap();
-            let material = World::create_material_from_toml(material_data, textures);
+            let material = World::create_material_from_toml(material_data, textures, seed);
             Box::new(Mesh::new(&Path::new(mesh_path), material))
         } else {
             panic!("Unknown object type");
@@ -285,6 +525,35 @@ impl World {
             let end = Vector3::new(r, g, b);
 
             return AmbientColor::Blended(start, end);
+        } else if ambient_type == "environment" {
+            let path = ambient_data["path"].as_str().unwrap();
+            let rotation = match ambient_data.get("rotation") {
+                Some(rotation) => rotation.as_float().unwrap() as f32,
+                None => 0.0,
+            };
+            let intensity = match ambient_data.get("intensity") {
+                Some(intensity) => intensity.as_float().unwrap() as f32,
+                None => 1.0,
+            };
+
+            let map = EnvironmentMap::load(Path::new(path), rotation, intensity).expect("Unable to load environment map");
+            return AmbientColor::Environment(Arc::new(map));
+        } else if ambient_type == "sky" {
+            let sun_direction = vector_from_toml(&ambient_data["sun_direction"]);
+            let turbidity = match ambient_data.get("turbidity") {
+                Some(turbidity) => turbidity.as_float().unwrap() as f32,
+                None => 3.0,
+            };
+            let ground_albedo = match ambient_data.get("ground_albedo") {
+                Some(ground_albedo) => vector_from_toml(ground_albedo),
+                None => Vector3::new(0.3, 0.3, 0.3),
+            };
+            let intensity = match ambient_data.get("intensity") {
+                Some(intensity) => intensity.as_float().unwrap() as f32,
+                None => 1.0,
+            };
+
+            return AmbientColor::Sky(Arc::new(Sky::new(sun_direction, turbidity, ground_albedo, intensity)));
         }
 
         AmbientColor::Constant(Vector3::zero())
@@ -296,70 +565,74 @@ impl World {
             AmbientColor::Blended(start, end) => {
                 let t = 0.5 * (ray.direction().y  + 1.0);
                 (1.0 - t) * start + t * end
-            }
+            },
+            AmbientColor::Environment(ref map) => map.radiance(ray.direction()),
+            AmbientColor::Sky(ref sky) => sky.radiance(ray.direction()),
         }
     }
 }
 
 impl Hitable for World {
     fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
-        let mut hit_record: Option<HitRecord> = None;
-        let mut closest_so_far = t_max;
-
-        for hitable in &self.hitables {
-            match hitable.hit(ray, t_min, closest_so_far) {
-                Some(hit) => {
-                    closest_so_far = hit.t();
-                    hit_record = Some(hit);
-                },
-                None => ()
-            }
+        match self.hit_object(ray, t_min, t_max) {
+            Some((hit, _)) => Some(hit),
+            None => None,
         }
-
-        hit_record
     }
 
     fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
-        if self.hitables.len() == 0 {
+        if self.unbounded.len() > 0 {
             return None;
         }
 
-        let mut bbox = match self.hitables[0].bounding_box(t0, t1) {
-            Some(aabb) => aabb,
-            None => return None,
-        };
-
-        for hitable in &self.hitables {
-            let aabb = match hitable.bounding_box(t0, t1) {
-                Some(aabb) => aabb,
-                None => return None,
-            };
-
-            bbox = AABB::surrounding_box(bbox, aabb);
+        match self.bvh {
+            Some(ref bvh) => bvh.bounding_box(t0, t1),
+            None => None,
         }
-
-        Some(bbox)
     }
 
     fn pdf_value(&self, origin: Vector3, v: Vector3) -> f32 {
-        let weight = 1.0 / self.hitables.len() as f32;
+        let total = self.len() as f32;
         let mut sum = 0.0;
-        for hitable in &self.hitables {
-            sum += weight * hitable.pdf_value(origin, v);
+
+        if let Some(ref bvh) = self.bvh {
+            sum += bvh.len() as f32 / total * bvh.pdf_value(origin, v);
+        }
+
+        for hitable in &self.unbounded {
+            sum += hitable.pdf_value(origin, v) / total;
         }
 
         sum
     }
 
-    fn random(&self, origin: Vector3) -> Vector3 {
-        let mut rng = rand::thread_rng();
-        let index = (rng.gen::<f32>() * self.hitables.len() as f32) as usize;
+    fn random(&self, origin: Vector3, sampler: &mut Sampler) -> Vector3 {
+        let index = ((sampler.next_1d() * self.len() as f32) as usize).min(self.len() - 1);
 
-        self.hitables[index].random(origin)
+        match self.bvh {
+            Some(ref bvh) if index < bvh.len() => bvh.random(origin, sampler),
+            Some(ref bvh) => self.unbounded[index - bvh.len()].random(origin, sampler),
+            None => self.unbounded[index].random(origin, sampler),
+        }
     }
 }
 
-enum AmbientColor {
+pub enum AmbientColor {
     Constant(Vector3),
     Blended(Vector3, Vector3),
+    Environment(Arc<EnvironmentMap>),
+    Sky(Arc<Sky>),
+}
+
+// Seed for a named scene item, so each procedural texture gets its own tables
+fn hash_str(seed: u32, name: &str) -> u32 {
+    name.bytes().fold(seed, |h, b| hash(h, b as u32))
+}
+
+fn vector_from_toml(value: &Value) -> Vector3 {
+    let values = value.as_array().unwrap();
+    let x = values[0].as_float().unwrap() as f32;
+    let y = values[1].as_float().unwrap() as f32;
+    let z = values[2].as_float().unwrap() as f32;
+    Vector3::new(x, y, z)
 }
\ No newline at end of file